@@ -0,0 +1,229 @@
+use std::process::ExitCode;
+
+use franta_rust::{client::Client, commands::Commands};
+
+const USAGE: &str = "usage: franta-rust [<command>] [--guild <id>]
+
+without a command the bot logs in
+
+commands:
+    list                  lists registered commands
+    delete <name>         deletes a registered command
+    delete --all          deletes every registered command
+    export <group>        prints the command group as JSON
+    register <group>      registers every command of the group
+    sync <group>          makes the registered commands match the group
+                          (--dry-run only prints what would change)
+
+groups: music, toulen
+commands are global unless --guild is given";
+
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    guild_id: Option<String>,
+    all: bool,
+    dry_run: bool,
+}
+
+fn parse(args: &[String]) -> Result<Args, String> {
+    let mut args = args.iter();
+
+    let command = args.next().ok_or("missing command")?.clone();
+
+    let mut parsed = Args {
+        command,
+        positional: vec![],
+        guild_id: None,
+        all: false,
+        dry_run: false,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--guild" => parsed.guild_id = Some(args.next().ok_or("missing guild id")?.clone()),
+            "--all" => parsed.all = true,
+            "--dry-run" => parsed.dry_run = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown flag {flag}")),
+            _ => parsed.positional.push(arg.clone()),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn group(args: &Args) -> Result<Commands, String> {
+    match args.positional.first().map(String::as_str) {
+        Some("music") => Ok(Commands::new()),
+        Some("toulen") => Ok(Commands::toulen()),
+        Some(group) => Err(format!("unknown command group {group}")),
+        None => Err(String::from("missing command group")),
+    }
+}
+
+/// Commands that don't talk to Discord, they run before the config is read.
+pub fn is_offline(args: &[String]) -> bool {
+    args.first().is_some_and(|command| command == "export")
+}
+
+pub fn run_offline(args: &[String]) -> ExitCode {
+    let args = match parse(args) {
+        Ok(args) => args,
+        Err(err) => return usage_error(&err),
+    };
+
+    exit_code(export(&args))
+}
+
+/// Runs a management command, returns the exit code for the process.
+pub async fn run(client: &Client, args: &[String]) -> ExitCode {
+    let args = match parse(args) {
+        Ok(args) => args,
+        Err(err) => return usage_error(&err),
+    };
+
+    let result = match args.command.as_str() {
+        "list" => list(client, &args).await,
+        "delete" => delete(client, &args).await,
+        "export" => export(&args),
+        "register" => register(client, &args).await,
+        "sync" => sync(client, &args).await,
+        command => return usage_error(&format!("unknown command {command}")),
+    };
+
+    exit_code(result)
+}
+
+fn usage_error(err: &str) -> ExitCode {
+    println!("{err}\n\n{USAGE}");
+
+    ExitCode::from(2)
+}
+
+fn exit_code(result: Result<(), String>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            println!("Error: {err}");
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn list(client: &Client, args: &Args) -> Result<(), String> {
+    let commands = client.registered_commands(args.guild_id.as_deref()).await?;
+
+    for command in &commands {
+        let (id, name, description) = (
+            command["id"].as_str().unwrap_or_default(),
+            command["name"].as_str().unwrap_or_default(),
+            command["description"].as_str().unwrap_or_default(),
+        );
+
+        println!("{id}  {name}  {description}");
+    }
+
+    println!("{} commands", commands.len());
+
+    Ok(())
+}
+
+async fn delete(client: &Client, args: &Args) -> Result<(), String> {
+    let guild_id = args.guild_id.as_deref();
+
+    if args.all {
+        client.delete_all_commands(guild_id).await?;
+
+        println!("deleted every command");
+
+        return Ok(());
+    }
+
+    let name = args.positional.first().ok_or("missing command name")?;
+
+    let commands = client.registered_commands(guild_id).await?;
+
+    let id = commands
+        .iter()
+        .find(|command| command["name"] == name.as_str())
+        .and_then(|command| command["id"].as_str())
+        .ok_or_else(|| format!("command {name} is not registered"))?;
+
+    client.delete_command(guild_id, id).await?;
+
+    println!("deleted {name}");
+
+    Ok(())
+}
+
+fn export(args: &Args) -> Result<(), String> {
+    let commands = group(args)?;
+
+    for command in &commands.commands {
+        command.definition.validate()?;
+    }
+
+    let json = serde_json::to_string_pretty(&commands.definitions())
+        .map_err(|err| format!("Error serializing commands:\n\n{err}"))?;
+
+    println!("{json}");
+
+    Ok(())
+}
+
+async fn register(client: &Client, args: &Args) -> Result<(), String> {
+    let commands = group(args)?;
+
+    let count = commands.commands.len();
+
+    for command in commands.definitions() {
+        match &args.guild_id {
+            Some(guild_id) => client.add_guild_command(guild_id.clone(), command).await?,
+            None => client.add_command(command).await?,
+        }
+    }
+
+    println!("registered {count} commands");
+
+    Ok(())
+}
+
+async fn sync(client: &Client, args: &Args) -> Result<(), String> {
+    let commands = group(args)?;
+
+    let report = client
+        .sync_commands(
+            args.guild_id.as_deref(),
+            &commands.definitions(),
+            args.dry_run,
+        )
+        .await?;
+
+    for name in &report.created {
+        println!("+ {name}");
+    }
+
+    for name in &report.updated {
+        println!("~ {name}");
+    }
+
+    for name in &report.removed {
+        println!("- {name}");
+    }
+
+    let (created, updated, removed, unchanged) = (
+        report.created.len(),
+        report.updated.len(),
+        report.removed.len(),
+        report.unchanged.len(),
+    );
+
+    let mode = if args.dry_run { " (dry run)" } else { "" };
+
+    println!(
+        "{created} created, {updated} updated, {removed} removed, {unchanged} unchanged{mode}"
+    );
+
+    Ok(())
+}
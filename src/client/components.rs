@@ -0,0 +1,525 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum ComponentType {
+    ActionRow,
+    Button,
+    StringSelect,
+    TextInput,
+    UserSelect,
+    RoleSelect,
+    MentionableSelect,
+    ChannelSelect,
+}
+
+impl From<ComponentType> for u8 {
+    fn from(component_type: ComponentType) -> Self {
+        match component_type {
+            ComponentType::ActionRow => 1,
+            ComponentType::Button => 2,
+            ComponentType::StringSelect => 3,
+            ComponentType::TextInput => 4,
+            ComponentType::UserSelect => 5,
+            ComponentType::RoleSelect => 6,
+            ComponentType::MentionableSelect => 7,
+            ComponentType::ChannelSelect => 8,
+        }
+    }
+}
+
+impl TryFrom<u8> for ComponentType {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(ComponentType::ActionRow),
+            2 => Ok(ComponentType::Button),
+            3 => Ok(ComponentType::StringSelect),
+            4 => Ok(ComponentType::TextInput),
+            5 => Ok(ComponentType::UserSelect),
+            6 => Ok(ComponentType::RoleSelect),
+            7 => Ok(ComponentType::MentionableSelect),
+            8 => Ok(ComponentType::ChannelSelect),
+            _ => Err(format!("unknown component type {value}")),
+        }
+    }
+}
+
+/// Any component that can be put into an action row.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum Component {
+    Button(Button),
+    SelectMenu(SelectMenu),
+    TextInput(TextInput),
+}
+
+// Discord tags components by the numeric `type` field, which serde can't do on its own
+impl<'de> Deserialize<'de> for Component {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let value = Value::deserialize(deserializer)?;
+
+        let component_type = value
+            .get("type")
+            .and_then(|t| t.as_u64())
+            .and_then(|t| ComponentType::try_from(t as u8).ok())
+            .ok_or_else(|| D::Error::custom("missing component type"))?;
+
+        match component_type {
+            ComponentType::Button => serde_json::from_value(value).map(Component::Button),
+            ComponentType::TextInput => serde_json::from_value(value).map(Component::TextInput),
+            ComponentType::ActionRow => {
+                return Err(D::Error::custom("action rows can't be nested"))
+            }
+            _ => serde_json::from_value(value).map(Component::SelectMenu),
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ActionRow {
+    #[serde(rename = "type")]
+    pub component_type: ComponentType,
+    pub components: Vec<Component>,
+}
+
+impl ActionRow {
+    pub fn new() -> Self {
+        Self {
+            component_type: ComponentType::ActionRow,
+            components: Vec::new(),
+        }
+    }
+
+    /// A row holds up to 5 buttons, or a single select menu or text input.
+    pub fn add_button(mut self, button: Button) -> Self {
+        self.components.push(Component::Button(button));
+        self
+    }
+
+    pub fn add_select_menu(mut self, menu: SelectMenu) -> Self {
+        self.components.push(Component::SelectMenu(menu));
+        self
+    }
+
+    pub fn add_text_input(mut self, input: TextInput) -> Self {
+        self.components.push(Component::TextInput(input));
+        self
+    }
+}
+
+impl Default for ActionRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum ButtonStyle {
+    Primary,
+    Secondary,
+    Success,
+    Danger,
+    Link,
+}
+
+impl From<ButtonStyle> for u8 {
+    fn from(style: ButtonStyle) -> Self {
+        match style {
+            ButtonStyle::Primary => 1,
+            ButtonStyle::Secondary => 2,
+            ButtonStyle::Success => 3,
+            ButtonStyle::Danger => 4,
+            ButtonStyle::Link => 5,
+        }
+    }
+}
+
+impl TryFrom<u8> for ButtonStyle {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(ButtonStyle::Primary),
+            2 => Ok(ButtonStyle::Secondary),
+            3 => Ok(ButtonStyle::Success),
+            4 => Ok(ButtonStyle::Danger),
+            5 => Ok(ButtonStyle::Link),
+            _ => Err(format!("unknown button style {value}")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ComponentEmoji {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub animated: bool,
+}
+
+impl ComponentEmoji {
+    pub fn unicode(name: &str) -> Self {
+        Self {
+            id: None,
+            name: Some(String::from(name)),
+            animated: false,
+        }
+    }
+
+    pub fn custom(id: String, name: String) -> Self {
+        Self {
+            id: Some(id),
+            name: Some(name),
+            animated: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Button {
+    #[serde(rename = "type")]
+    pub component_type: ComponentType,
+    pub style: ButtonStyle,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<ComponentEmoji>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+impl Button {
+    /// Link buttons have no `custom_id`, use [`Button::link`] for them.
+    pub fn new(style: ButtonStyle, custom_id: String) -> Self {
+        Self {
+            component_type: ComponentType::Button,
+            style,
+            label: None,
+            emoji: None,
+            custom_id: Some(custom_id),
+            url: None,
+            disabled: false,
+        }
+    }
+
+    pub fn link(url: String) -> Self {
+        Self {
+            component_type: ComponentType::Button,
+            style: ButtonStyle::Link,
+            label: None,
+            emoji: None,
+            custom_id: None,
+            url: Some(url),
+            disabled: false,
+        }
+    }
+
+    pub fn set_label(mut self, label: &str) -> Self {
+        self.label = Some(String::from(label));
+        self
+    }
+
+    pub fn set_emoji(mut self, emoji: ComponentEmoji) -> Self {
+        self.emoji = Some(emoji);
+        self
+    }
+
+    pub fn set_disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SelectMenu {
+    #[serde(rename = "type")]
+    pub component_type: ComponentType,
+    pub custom_id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<SelectOption>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_types: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_values: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_values: Option<u8>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+impl SelectMenu {
+    fn new(component_type: ComponentType, custom_id: String) -> Self {
+        Self {
+            component_type,
+            custom_id,
+            options: Vec::new(),
+            channel_types: None,
+            placeholder: None,
+            min_values: None,
+            max_values: None,
+            disabled: false,
+        }
+    }
+
+    pub fn string(custom_id: String) -> Self {
+        Self::new(ComponentType::StringSelect, custom_id)
+    }
+
+    pub fn user(custom_id: String) -> Self {
+        Self::new(ComponentType::UserSelect, custom_id)
+    }
+
+    pub fn channel(custom_id: String) -> Self {
+        Self::new(ComponentType::ChannelSelect, custom_id)
+    }
+
+    pub fn set_placeholder(mut self, placeholder: &str) -> Self {
+        self.placeholder = Some(String::from(placeholder));
+        self
+    }
+
+    /// Only used by string selects, the other ones are filled in by Discord.
+    pub fn add_option(mut self, option: SelectOption) -> Self {
+        self.options.push(option);
+        self
+    }
+
+    /// Only used by channel selects, e.g. `2` for voice channels.
+    pub fn set_channel_types(mut self, channel_types: Vec<u8>) -> Self {
+        self.channel_types = Some(channel_types);
+        self
+    }
+
+    pub fn set_min_values(mut self, min_values: u8) -> Self {
+        self.min_values = Some(min_values);
+        self
+    }
+
+    pub fn set_max_values(mut self, max_values: u8) -> Self {
+        self.max_values = Some(max_values);
+        self
+    }
+
+    pub fn set_disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SelectOption {
+    pub label: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<ComponentEmoji>,
+    #[serde(default)]
+    pub default: bool,
+}
+
+impl SelectOption {
+    pub fn new(label: String, value: String) -> Self {
+        Self {
+            label,
+            value,
+            description: None,
+            emoji: None,
+            default: false,
+        }
+    }
+
+    pub fn set_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn set_emoji(mut self, emoji: ComponentEmoji) -> Self {
+        self.emoji = Some(emoji);
+        self
+    }
+
+    pub fn set_default(mut self, default: bool) -> Self {
+        self.default = default;
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum TextInputStyle {
+    Short,
+    Paragraph,
+}
+
+impl From<TextInputStyle> for u8 {
+    fn from(style: TextInputStyle) -> Self {
+        match style {
+            TextInputStyle::Short => 1,
+            TextInputStyle::Paragraph => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for TextInputStyle {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(TextInputStyle::Short),
+            2 => Ok(TextInputStyle::Paragraph),
+            _ => Err(format!("unknown text input style {value}")),
+        }
+    }
+}
+
+/// Only allowed inside modals.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TextInput {
+    #[serde(rename = "type")]
+    pub component_type: ComponentType,
+    pub custom_id: String,
+    pub style: TextInputStyle,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u16>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+}
+
+impl TextInput {
+    pub fn new(custom_id: String, style: TextInputStyle, label: &str) -> Self {
+        Self {
+            component_type: ComponentType::TextInput,
+            custom_id,
+            style,
+            label: String::from(label),
+            min_length: None,
+            max_length: None,
+            required: true,
+            value: None,
+            placeholder: None,
+        }
+    }
+
+    pub fn set_min_length(mut self, min_length: u16) -> Self {
+        self.min_length = Some(min_length);
+        self
+    }
+
+    pub fn set_max_length(mut self, max_length: u16) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    pub fn set_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    pub fn set_value(mut self, value: String) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    pub fn set_placeholder(mut self, placeholder: &str) -> Self {
+        self.placeholder = Some(String::from(placeholder));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{
+        ActionRow, Button, ButtonStyle, Component, ComponentEmoji, SelectMenu, SelectOption,
+        TextInput, TextInputStyle,
+    };
+
+    fn rows() -> Vec<ActionRow> {
+        vec![
+            ActionRow::new()
+                .add_button(
+                    Button::new(ButtonStyle::Primary, String::from("pause"))
+                        .set_emoji(ComponentEmoji::unicode("⏯"))
+                        .set_label("Pause"),
+                )
+                .add_button(Button::link(String::from("https://example.com")).set_disabled(true)),
+            ActionRow::new().add_select_menu(
+                SelectMenu::string(String::from("loop"))
+                    .set_placeholder("Loop")
+                    .set_max_values(1)
+                    .add_option(
+                        SelectOption::new(String::from("Off"), String::from("off"))
+                            .set_description(String::from("no looping"))
+                            .set_default(true),
+                    ),
+            ),
+            ActionRow::new().add_select_menu(
+                SelectMenu::channel(String::from("channel")).set_channel_types(vec![2]),
+            ),
+            ActionRow::new().add_text_input(
+                TextInput::new(String::from("name"), TextInputStyle::Short, "Name")
+                    .set_max_length(100)
+                    .set_required(false),
+            ),
+        ]
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let json = serde_json::to_value(rows()).unwrap();
+
+        let rows: Vec<ActionRow> = serde_json::from_value(json.clone()).unwrap();
+
+        assert!(matches!(rows[0].components[0], Component::Button(_)));
+        assert!(matches!(rows[1].components[0], Component::SelectMenu(_)));
+        assert!(matches!(rows[2].components[0], Component::SelectMenu(_)));
+        assert!(matches!(rows[3].components[0], Component::TextInput(_)));
+
+        assert_eq!(serde_json::to_value(rows).unwrap(), json);
+    }
+
+    #[test]
+    fn components_are_tagged_by_type() {
+        let json = serde_json::to_value(rows()).unwrap();
+
+        assert_eq!(json[0]["type"], 1);
+        assert_eq!(json[0]["components"][0]["type"], 2);
+        assert_eq!(json[0]["components"][0]["style"], 1);
+        assert_eq!(json[1]["components"][0]["type"], 3);
+        assert_eq!(json[2]["components"][0]["type"], 8);
+        assert_eq!(json[3]["components"][0]["type"], 4);
+    }
+
+    #[test]
+    fn nested_rows_and_unknown_types_are_rejected() {
+        let nested = json!({ "type": 1, "components": [{ "type": 1, "components": [] }] });
+        let unknown = json!({ "type": 1, "components": [{ "type": 42 }] });
+
+        assert!(serde_json::from_value::<ActionRow>(nested).is_err());
+        assert!(serde_json::from_value::<ActionRow>(unknown).is_err());
+    }
+}
@@ -1,7 +1,8 @@
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Instant;
 
-use crate::client::{Event, LavalinkBuilderOptions};
+use crate::client::{Event, LavalinkBuilderOptions, LavalinkVersion};
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
 use http::Request;
@@ -17,6 +18,12 @@ use tokio_tungstenite::{
     WebSocketStream,
 };
 
+/// A node that accepts the connection but never answers would otherwise keep its
+/// players waiting forever.
+const NODE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long lavalink keeps the players of a closed session for us.
+const RESUME_TIMEOUT: Duration = Duration::from_secs(60);
+
 type WsStreamType = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
 type Write = SplitSink<WsStreamType, Message>;
 type Read = SplitStream<WsStreamType>;
@@ -75,7 +82,7 @@ fn create_event(event_name: &str, value: &Value) -> Result<Event, String> {
 
             let event = serde_json::from_value(interaction.to_owned()).map_err(|_| err_parse)?;
 
-            Ok(Event::InteractionCreate(event))
+            Ok(Event::InteractionCreate(Box::new(event)))
         }
 
         "VOICE_STATE_UPDATE" => {
@@ -361,10 +368,131 @@ impl DiscordEvLoop {
     }
 }
 
+/// Everything a `Player` can ask of its lavalink node. v3 nodes only understand `Ws`,
+/// v4 nodes drive players through the REST api instead.
+#[derive(Debug)]
+pub enum LavalinkPayload {
+    Ws(String),
+    Update { guild_id: String, body: Value },
+    Destroy { guild_id: String },
+}
+
+async fn get_session_id(ws_stream: &mut WsStreamType) -> Result<(String, bool), ()> {
+    while let Some(Ok(msg)) = ws_stream.next().await {
+        let msg = match msg {
+            Message::Text(msg) => msg,
+            _ => continue,
+        };
+
+        let msg: Value = serde_json::from_str(&msg).map_err(|_| ())?;
+
+        if msg.get("op").and_then(|op| op.as_str()) != Some("ready") {
+            continue;
+        }
+
+        let session_id = msg.get("sessionId").and_then(|id| id.as_str()).ok_or(())?;
+
+        let resumed = msg
+            .get("resumed")
+            .and_then(|r| r.as_bool())
+            .unwrap_or(false);
+
+        return Ok((String::from(session_id), resumed));
+    }
+
+    Err(())
+}
+
+fn create_lavalink_event(node: &str, value: &Value) -> Result<Event, String> {
+    let op = value
+        .get("op")
+        .and_then(|op| op.as_str())
+        .ok_or("Failed to create lavalink event: Failed to get op")?;
+
+    let err_get = format!("Failed to create lavalink {op} event: Failed to get data");
+    let err_parse = format!("Failed to create lavalink {op} event: Failed to parse data");
+
+    match op {
+        "stats" => {
+            let stats = serde_json::from_value(value.to_owned()).map_err(|_| err_parse)?;
+
+            Ok(Event::LavalinkStats(String::from(node), stats))
+        }
+
+        "playerUpdate" => {
+            let guild_id = value
+                .get("guildId")
+                .and_then(|id| id.as_str())
+                .ok_or(&err_get)?;
+
+            let state = value.get("state").ok_or(&err_get)?;
+
+            let state = serde_json::from_value(state.to_owned()).map_err(|_| err_parse)?;
+
+            Ok(Event::PlayerUpdate(String::from(guild_id), state))
+        }
+
+        "event" => {
+            let guild_id = value
+                .get("guildId")
+                .and_then(|id| id.as_str())
+                .ok_or(&err_get)?;
+
+            let guild_id = String::from(guild_id);
+
+            match value.get("type").and_then(|t| t.as_str()) {
+                Some("TrackStartEvent") => Ok(Event::TrackStart(guild_id)),
+
+                Some("TrackEndEvent") => {
+                    let reason = value.get("reason").ok_or(&err_get)?;
+
+                    let reason =
+                        serde_json::from_value(reason.to_owned()).map_err(|_| err_parse)?;
+
+                    Ok(Event::TrackEnd(guild_id, reason))
+                }
+
+                Some("TrackExceptionEvent") => {
+                    let exception = value.get("exception").ok_or(&err_get)?;
+
+                    let exception =
+                        serde_json::from_value(exception.to_owned()).map_err(|_| err_parse)?;
+
+                    Ok(Event::TrackException(guild_id, exception))
+                }
+
+                Some("TrackStuckEvent") => {
+                    let threshold = value
+                        .get("thresholdMs")
+                        .and_then(|t| t.as_u64())
+                        .ok_or(&err_get)?;
+
+                    Ok(Event::TrackStuck(guild_id, threshold))
+                }
+
+                Some("WebSocketClosedEvent") => {
+                    let closed = serde_json::from_value(value.to_owned()).map_err(|_| err_parse)?;
+
+                    Ok(Event::WebSocketClosed(guild_id, closed))
+                }
+
+                Some(event_type) => Err(format!("Lavalink event {event_type} is not handled")),
+                None => Err(err_get),
+            }
+        }
+
+        _ => Err(format!("Lavalink op {op} was not found")),
+    }
+}
+
 #[derive(Debug)]
 pub struct LavalinkEvLoop {
-    pub sender: Option<Arc<UnboundedSender<String>>>,
+    pub sender: Option<Arc<UnboundedSender<LavalinkPayload>>>,
     pub options: Rc<LavalinkBuilderOptions>,
+    session_id: Option<String>,
+    /// Until when a v3 node keeps the closed session, v3 never says whether
+    /// the Resume-Key resumed it.
+    resumable_until: Option<Instant>,
     handles: Vec<JoinHandle<()>>,
 }
 
@@ -373,64 +501,63 @@ impl LavalinkEvLoop {
         Self {
             sender: None,
             options: Rc::new(options),
+            session_id: None,
+            resumable_until: None,
             handles: vec![],
         }
     }
 
-    pub async fn connect(&mut self, tx: Arc<UnboundedSender<Event>>) -> Result<(), String> {
-        let (host, port) = (&self.options.host, self.options.port);
+    /// Connects to the node, returns whether lavalink resumed the previous session
+    /// and so still has its players.
+    pub async fn connect(&mut self, tx: Arc<UnboundedSender<Event>>) -> Result<bool, String> {
+        let connection = self.connector().connect().await?;
 
-        let ws_uri = format!("ws://{host}:{port}/");
+        Ok(self.attach(connection, tx))
+    }
 
-        let url = Request::builder()
-            .method("GET")
-            .uri(&ws_uri)
-            .header("Host", &self.options.host)
-            .header("Connection", "Upgrade")
-            .header("Upgrade", "websocket")
-            .header("Sec-WebSocket-Version", "13")
-            .header("Sec-WebSocket-Key", generate_key())
-            .header("Authorization", &self.options.password)
-            .header(
-                "User-Id",
-                "1044312701637247087", /*self.options.user_id*/
-            )
-            .header("Client-Name", "franta-rust")
-            .header("Resume-Key", "franta-rust-resume-key")
-            .body(())
-            .map_err(|_| "Failed to create request")?;
+    /// Everything needed to connect, so the connecting can run in its own task.
+    pub fn connector(&self) -> NodeConnector {
+        NodeConnector {
+            name: self.options.name.clone(),
+            host: self.options.host.clone(),
+            port: self.options.port,
+            password: self.options.password.clone(),
+            version: self.options.version,
+            session_id: self.session_id.clone(),
+            resumable_until: self.resumable_until,
+        }
+    }
 
-        let (ws_stream, _) = connect_async(url)
-            .await
-            .map_err(|_| "Failed to connect to the lavalink")?;
+    /// The connection was lost. Every v3 connection configures resuming, so the
+    /// node keeps the session for `RESUME_TIMEOUT` from now.
+    pub fn closed(&mut self) {
+        if self.options.version == LavalinkVersion::V3 && !self.handles.is_empty() {
+            self.resumable_until = Some(Instant::now() + RESUME_TIMEOUT);
+        }
+    }
 
-        let (mut write, mut read) = ws_stream.split();
+    /// Starts using a new connection to the node, returns whether it resumed the
+    /// previous session.
+    pub fn attach(&mut self, connection: NodeConnection, tx: Arc<UnboundedSender<Event>>) -> bool {
+        if connection.session_id.is_some() {
+            self.session_id = connection.session_id;
+        }
 
-        let (sender, rx) = mpsc::unbounded_channel::<String>();
+        self.resumable_until = None;
 
-        let sender = Arc::new(sender);
+        let (write, mut read) = connection.ws_stream.split();
 
-        self.sender = Some(Arc::clone(&sender));
+        let (sender, rx) = mpsc::unbounded_channel::<LavalinkPayload>();
 
-        if let Err(err) = write
-            .send(Message::Text(
-                json!({
-                    "op": "configureResuming",
-                    "key": "franta-rust-resume-key",
-                    "timeout": 60
-                })
-                .to_string(),
-            ))
-            .await
-        {
-            return Err(format!("Failed to send resume config: {err}"));
-        };
+        self.sender = Some(Arc::new(sender));
 
         let handle = self.recv(rx, write);
 
         self.handles.push(handle);
 
-        tokio::spawn(async move {
+        let name = self.options.name.clone();
+
+        let handle = tokio::spawn(async move {
             while let Some(Ok(resp)) = read.next().await {
                 match resp {
                     Message::Text(msg) => {
@@ -439,41 +566,34 @@ impl LavalinkEvLoop {
                             _ => continue,
                         };
 
-                        let guild_id = match parsed_msg.get("guildId").and_then(|id| id.as_str()) {
-                            Some(guild_id) => guild_id,
+                        let event = match create_lavalink_event(&name, &parsed_msg) {
+                            Ok(event) => event,
                             _ => continue,
                         };
 
-                        if let Some(event_type) = parsed_msg.get("type") {
-                            match event_type.as_str() {
-                                Some("TrackEndEvent") => {
-                                    if let Err(err) =
-                                        tx.send(Event::TrackEnd(String::from(guild_id)))
-                                    {
-                                        println!("lavalink: {err}");
-                                    }
-                                }
-                                _ => continue,
-                            }
-                        };
+                        if let Err(err) = tx.send(event) {
+                            println!("lavalink: {err}");
+                        }
                     }
                     Message::Close(close) => {
                         println!("Close: {close:?}");
 
-                        if let Err(err) = tx.send(Event::LavalinkClosed) {
-                            println!("lavalink: {err}");
-                        }
-
                         break;
                     }
                     _ => (),
                 }
             }
 
-            println!("lavalink connection closed");
+            println!("lavalink {name} connection closed");
+
+            if let Err(err) = tx.send(Event::LavalinkClosed(name)) {
+                println!("lavalink: {err}");
+            }
         });
 
-        Ok(())
+        self.handles.push(handle);
+
+        connection.resumed
     }
 
     pub fn abort_tasks(&mut self) {
@@ -484,13 +604,190 @@ impl LavalinkEvLoop {
         self.handles.clear();
     }
 
-    fn recv(&self, mut rx: UnboundedReceiver<String>, mut write: Write) -> JoinHandle<()> {
+    fn recv(&self, mut rx: UnboundedReceiver<LavalinkPayload>, mut write: Write) -> JoinHandle<()> {
+        let (host, port) = (&self.options.host, self.options.port);
+
+        let session_url = self
+            .session_id
+            .as_ref()
+            .map(|session_id| format!("http://{host}:{port}/v4/sessions/{session_id}"));
+
+        let password = self.options.password.clone();
+
         tokio::spawn(async move {
+            let client = reqwest::Client::new();
+
             while let Some(payload) = rx.recv().await {
-                if let Err(err) = write.send(Message::Text(payload)).await {
-                    println!("{err}")
+                let request = match (payload, &session_url) {
+                    (LavalinkPayload::Ws(payload), _) => {
+                        if let Err(err) = write.send(Message::Text(payload)).await {
+                            println!("{err}")
+                        };
+
+                        continue;
+                    }
+                    (LavalinkPayload::Update { guild_id, body }, Some(url)) => client
+                        .patch(format!("{url}/players/{guild_id}"))
+                        .json(&body),
+                    (LavalinkPayload::Destroy { guild_id }, Some(url)) => {
+                        client.delete(format!("{url}/players/{guild_id}"))
+                    }
+                    (_, None) => {
+                        println!("lavalink: rest payload sent without a session");
+
+                        continue;
+                    }
                 };
+
+                match request.header("Authorization", &password).send().await {
+                    Ok(res) if !res.status().is_success() => {
+                        let status = res.status();
+
+                        println!("lavalink: Err: {status}, while updating player");
+                    }
+                    Err(err) => println!("lavalink: {err}"),
+                    _ => (),
+                }
             }
         })
     }
 }
+
+/// A connection to a node that is ready to be attached to its `LavalinkEvLoop`.
+#[derive(Debug)]
+pub struct NodeConnection {
+    ws_stream: WsStreamType,
+    session_id: Option<String>,
+    resumed: bool,
+}
+
+#[derive(Debug)]
+pub struct NodeConnector {
+    name: String,
+    host: String,
+    port: u16,
+    password: String,
+    version: LavalinkVersion,
+    session_id: Option<String>,
+    resumable_until: Option<Instant>,
+}
+
+impl NodeConnector {
+    pub async fn connect(self) -> Result<NodeConnection, String> {
+        let (host, port) = (&self.host, self.port);
+
+        let ws_uri = match self.version {
+            LavalinkVersion::V3 => format!("ws://{host}:{port}/"),
+            LavalinkVersion::V4 => format!("ws://{host}:{port}/v4/websocket"),
+        };
+
+        let mut request = Request::builder()
+            .method("GET")
+            .uri(&ws_uri)
+            .header("Host", host)
+            .header("Connection", "Upgrade")
+            .header("Upgrade", "websocket")
+            .header("Sec-WebSocket-Version", "13")
+            .header("Sec-WebSocket-Key", generate_key())
+            .header("Authorization", &self.password)
+            .header(
+                "User-Id",
+                "1044312701637247087", /*self.options.user_id*/
+            )
+            .header("Client-Name", "franta-rust");
+
+        request = match (self.version, &self.session_id) {
+            (LavalinkVersion::V3, _) => request.header("Resume-Key", "franta-rust-resume-key"),
+            (LavalinkVersion::V4, Some(session_id)) => request.header("Session-Id", session_id),
+            (LavalinkVersion::V4, None) => request,
+        };
+
+        let url = request.body(()).map_err(|_| "Failed to create request")?;
+
+        let (mut ws_stream, _) = time::timeout(NODE_TIMEOUT, connect_async(url))
+            .await
+            .map_err(|_| "Timed out connecting to the lavalink")?
+            .map_err(|_| "Failed to connect to the lavalink")?;
+
+        match self.version {
+            LavalinkVersion::V3 => {
+                if let Err(err) = ws_stream
+                    .send(Message::Text(
+                        json!({
+                            "op": "configureResuming",
+                            "key": "franta-rust-resume-key",
+                            "timeout": RESUME_TIMEOUT.as_secs()
+                        })
+                        .to_string(),
+                    ))
+                    .await
+                {
+                    return Err(format!("Failed to send resume config: {err}"));
+                };
+
+                // the Resume-Key resumes a session that was configured and hasn't
+                // timed out yet, replaying on top of it would restart every track
+                let resumed = self
+                    .resumable_until
+                    .is_some_and(|until| Instant::now() < until);
+
+                if resumed {
+                    println!("lavalink {} session resumed", self.name);
+                }
+
+                Ok(NodeConnection {
+                    ws_stream,
+                    session_id: None,
+                    resumed,
+                })
+            }
+            LavalinkVersion::V4 => {
+                let (session_id, resumed) =
+                    time::timeout(NODE_TIMEOUT, get_session_id(&mut ws_stream))
+                        .await
+                        .map_err(|_| "Timed out waiting for the lavalink session id")?
+                        .map_err(|_| "Failed to get lavalink session id")?;
+
+                if resumed {
+                    println!("lavalink {} session {session_id} resumed", self.name);
+                }
+
+                self.configure_resuming(&session_id).await?;
+
+                Ok(NodeConnection {
+                    ws_stream,
+                    session_id: Some(session_id),
+                    resumed,
+                })
+            }
+        }
+    }
+
+    async fn configure_resuming(&self, session_id: &str) -> Result<(), String> {
+        let (host, port) = (&self.host, self.port);
+
+        let url = format!("http://{host}:{port}/v4/sessions/{session_id}");
+
+        let res = reqwest::Client::new()
+            .patch(url)
+            .timeout(NODE_TIMEOUT)
+            .header("Authorization", &self.password)
+            .json(&json!({
+                "resuming": true,
+                "timeout": RESUME_TIMEOUT.as_secs()
+            }))
+            .send()
+            .await
+            .map_err(|err| format!("Failed to send resume config: {err}"))?;
+
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            let status = res.status();
+
+            Err(format!(
+                "Err: {status}, while configuring lavalink resuming"
+            ))
+        }
+    }
+}
@@ -1,21 +1,37 @@
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use super::components::{ActionRow, ComponentType, TextInput};
+use super::event_loop::NodeConnection;
+use super::rest::{self, Rest};
+use crate::commands::builder::{ApplicationCommandOptionChoice, OptionType};
+use crate::locale::Locales;
+
 #[derive(Debug)]
 pub enum Event {
     Ready(ReadyUser),
     Resume,
     Reconnect,
-    InteractionCreate(Interaction),
+    InteractionCreate(Box<Interaction>),
     VoiceStateUpdate(VoiceState),
     VoiceServerUpdate(VoiceServer),
     ResumeSeq(u64),
     ResumeProps((String, String)),
     SendWS(String),
-    LavalinkClosed,
-    TrackEnd(String),
+    LavalinkClosed(String),
+    LavalinkReconnect(String),
+    LavalinkConnected(String, Box<Result<NodeConnection, String>>),
+    LavalinkStats(String, NodeStats),
+    PlayerUpdate(String, PlayerState),
+    TrackStart(String),
+    TrackEnd(String, TrackEndReason),
+    TrackException(String, TrackException),
+    TrackStuck(String, u64),
+    WebSocketClosed(String, WebSocketClosed),
     DestroyPlayer(String),
 }
 
@@ -38,34 +54,31 @@ pub struct DiscordMessage {
     pub id: String,
     pub member: Member,
     pub author: Author,
-    token: Option<String>,
+    #[serde(skip)]
+    rest: Option<Arc<Rest>>,
 }
 
 impl DiscordMessage {
     pub async fn channel(&self) -> Result<Channel, String> {
         let channel_id = &self.channel_id;
 
-        let url = format!("https://discord.com/api/v10/channels/{channel_id}");
-
-        let token = self.token.as_ref().ok_or("Token not found")?;
-
-        let client = reqwest::Client::new();
-
-        let mut res: Channel = client
-            .get(url)
-            // .bearer_auth(token)
-            .header("Authorization", format!("Bot {token}"))
-            .send()
+        let mut res: Channel = rest::required(&self.rest)?
+            .get(&format!("/channels/{channel_id}"))
             .await
             .map_err(|err| format!("Error fetching channel:\n\n{err}"))?
             .json()
             .await
             .map_err(|err| format!("Error parsing channel:\n\n{err}"))?;
 
-        res.token = Some(String::from(token));
+        res.rest = self.rest.clone();
 
         Ok(res)
     }
+
+    pub fn set_rest(mut self, rest: Arc<Rest>) -> Self {
+        self.rest = Some(rest);
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -83,30 +96,114 @@ pub struct VoiceServer {
     pub endpoint: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStats {
+    pub players: u32,
+    pub playing_players: u32,
+    pub uptime: u64,
+    pub cpu: CpuStats,
+    pub frame_stats: Option<FrameStats>,
+}
+
+impl NodeStats {
+    /// Same weighting as the reference lavalink clients use for load balancing.
+    pub fn penalty(&self) -> f64 {
+        let players = self.playing_players as f64;
+
+        let cpu = 1.05f64.powf(100.0 * self.cpu.system_load) * 10.0 - 10.0;
+
+        let (deficit, nulled) = match &self.frame_stats {
+            Some(frames) => (
+                1.03f64.powf(500.0 * (frames.deficit as f64 / 3000.0)) * 600.0 - 600.0,
+                (1.03f64.powf(500.0 * (frames.nulled as f64 / 3000.0)) * 300.0 - 300.0) * 2.0,
+            ),
+            None => (0.0, 0.0),
+        };
+
+        players + cpu + deficit + nulled
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuStats {
+    pub cores: u32,
+    pub system_load: f64,
+    pub lavalink_load: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FrameStats {
+    pub sent: i64,
+    pub nulled: i64,
+    pub deficit: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlayerState {
+    #[serde(default)]
+    pub time: u64,
+    #[serde(default)]
+    pub position: u64,
+    #[serde(default)]
+    pub connected: bool,
+    #[serde(default)]
+    pub ping: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackEndReason {
+    #[serde(rename = "FINISHED", alias = "finished")]
+    Finished,
+    #[serde(rename = "LOAD_FAILED", alias = "loadFailed")]
+    LoadFailed,
+    #[serde(rename = "STOPPED", alias = "stopped")]
+    Stopped,
+    #[serde(rename = "REPLACED", alias = "replaced")]
+    Replaced,
+    #[serde(rename = "CLEANUP", alias = "cleanup")]
+    Cleanup,
+}
+
+impl TrackEndReason {
+    /// Lavalink's `mayStartNext`, only a finished or failed track should move the queue.
+    pub fn may_start_next(&self) -> bool {
+        matches!(self, Self::Finished | Self::LoadFailed)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrackException {
+    pub message: Option<String>,
+    pub severity: String,
+    pub cause: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebSocketClosed {
+    pub code: u16,
+    pub reason: String,
+    #[serde(rename = "byRemote")]
+    pub by_remote: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Channel {
     pub id: String,
     pub name: String,
-    token: Option<String>,
+    #[serde(skip)]
+    rest: Option<Arc<Rest>>,
 }
 
 impl Channel {
     pub async fn send(&self, content: &str) -> Result<(), String> {
         let channel_id = &self.id;
 
-        let url = format!("https://discord.com/api/v10/channels/{channel_id}/messages");
-
-        let token = self.token.as_ref().ok_or("Token not found")?;
-
-        let client = reqwest::Client::new();
-
         let body = json!({ "content": content });
 
-        client
-            .post(url)
-            .header("Authorization", format!("Bot {token}"))
-            .json(&body)
-            .send()
+        rest::required(&self.rest)?
+            .post(&format!("/channels/{channel_id}/messages"), &body)
             .await
             .map_err(|err| format!("Error sending message:\n\n{err}"))?;
 
@@ -121,6 +218,8 @@ pub struct User {
     pub id: String,
     pub public_flags: u32,
     pub username: String,
+    #[serde(default)]
+    pub global_name: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -129,16 +228,37 @@ pub struct Member {
     pub nick: Option<String>,
     pub roles: Vec<String>,
     pub user: User,
+    #[serde(default)]
+    pub permissions: Option<String>,
     #[serde(skip)]
     pub voice: Option<Arc<VoiceState>>,
 }
 
+pub const PERMISSION_ADMINISTRATOR: u64 = 1 << 3;
+pub const PERMISSION_MANAGE_CHANNELS: u64 = 1 << 4;
+pub const PERMISSION_MANAGE_GUILD: u64 = 1 << 5;
+
 impl Member {
-    pub fn get_voice_channel(&self) -> Option<&String> {
-        self.voice
+    pub fn display_name(&self) -> &str {
+        self.nick
+            .as_deref()
+            .or(self.user.global_name.as_deref())
+            .unwrap_or(&self.user.username)
+    }
+
+    /// Only interactions carry the member's computed permissions.
+    pub fn has_permission(&self, permission: u64) -> bool {
+        let permissions = self
+            .permissions
             .as_ref()
-            .and_then(|v| v.channel_id.as_ref())
-            .and_then(|c| Some(c))
+            .and_then(|p| p.parse::<u64>().ok())
+            .unwrap_or(0);
+
+        permissions & (permission | PERMISSION_ADMINISTRATOR) != 0
+    }
+
+    pub fn get_voice_channel(&self) -> Option<&String> {
+        self.voice.as_ref().and_then(|v| v.channel_id.as_ref())
     }
 }
 
@@ -161,12 +281,36 @@ pub struct Interaction {
     pub channel_id: String,
     pub member: Member,
     pub token: String,
+    /// Language of the member's client.
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub guild_locale: Option<String>,
+    #[serde(skip)]
+    rest: Option<Arc<Rest>>,
+}
+
+fn rec_focused(options: &[InteractionDataOption]) -> Option<(&str, &Value)> {
+    for option in options {
+        if option.focused {
+            return Some((option.name.as_str(), option.value.as_ref()?));
+        }
+
+        if let Some(found) = option.options.as_deref().and_then(rec_focused) {
+            return Some(found);
+        }
+    }
+
+    None
 }
 
 fn rec_options<'i>(options: &'i Vec<InteractionDataOption>, name: &str) -> Option<&'i Value> {
     for option in options {
+        // subcommands and groups have no value, only nested options
         if option.name.as_str() == name {
-            return Some(option.value.as_ref().unwrap());
+            if let Some(value) = option.value.as_ref() {
+                return Some(value);
+            }
         }
 
         if let Some(opts) = option.options.as_ref() {
@@ -182,57 +326,119 @@ fn rec_options<'i>(options: &'i Vec<InteractionDataOption>, name: &str) -> Optio
 
 impl Interaction {
     pub async fn ack(&self, flags: u32) -> Result<(), String> {
-        let (int_id, token) = (&self.id, &self.token);
+        self.callback(5, InteractionCallbackData::new().set_flags(flags))
+            .await
+    }
 
-        let url = format!("https://discord.com/api/v10/interactions/{int_id}/{token}/callback");
+    /// Acknowledges a component interaction, the message it came from gets edited later.
+    pub async fn ack_update(&self) -> Result<(), String> {
+        self.callback(6, InteractionCallbackData::new()).await
+    }
 
-        let client = reqwest::Client::new();
+    /// Answers an autocomplete interaction, there is no ack before it.
+    pub async fn autocomplete(
+        &self,
+        choices: Vec<ApplicationCommandOptionChoice>,
+    ) -> Result<(), String> {
+        self.callback(8, json!({ "choices": choices })).await
+    }
+
+    pub async fn modal(&self, modal: Modal) -> Result<(), String> {
+        self.callback(9, modal).await
+    }
+
+    async fn callback<T: Serialize>(&self, interaction_type: u32, data: T) -> Result<(), String> {
+        let (int_id, token) = (&self.id, &self.token);
 
         let body = InteractionCallback {
-            interaction_type: 5,
-            data: InteractionCallbackData::new().set_flags(flags),
+            interaction_type,
+            data,
         };
 
-        let res = client
-            .post(url)
-            .json(&body)
-            .send()
+        rest::required(&self.rest)?
+            .post(&format!("/interactions/{int_id}/{token}/callback"), &body)
             .await
-            .map_err(|_| "Error sending ack")?;
+            .map_err(|err| format!("{err}, while trying to ack interaction"))?;
 
-        if res.status().is_success() {
-            Ok(())
-        } else {
-            let status = res.status();
+        Ok(())
+    }
 
-            Err(format!("Err: {status}, while trying to ack interaction"))
-        }
+    /// Answers right away, for when there is nothing slow to wait for.
+    pub async fn respond(&self, data: InteractionCallbackData) -> Result<(), String> {
+        self.callback(4, data).await
     }
 
-    pub async fn create_message(&self, body: InteractionCallbackData) -> Result<(), String> {
-        let (app_id, token) = (&self.application_id, &self.token);
+    /// Edits the deferred or immediate response to the interaction.
+    pub async fn edit_original(&self, body: InteractionCallbackData) -> Result<(), String> {
+        self.webhook(reqwest::Method::PATCH, "messages/@original", Some(&body))
+            .await
+            .map(|_| ())
+    }
 
-        let url =
-            format!("https://discord.com/api/v10/webhooks/{app_id}/{token}/messages/@original");
+    pub async fn delete_original(&self) -> Result<(), String> {
+        self.webhook::<()>(reqwest::Method::DELETE, "messages/@original", None)
+            .await
+            .map(|_| ())
+    }
 
-        let client = reqwest::Client::new();
+    /// Sends another message after the original response, returns its id so it can be edited.
+    pub async fn create_followup(&self, body: InteractionCallbackData) -> Result<String, String> {
+        let res = self.webhook(reqwest::Method::POST, "", Some(&body)).await?;
 
-        let res = client
-            .patch(url)
-            .json(&body)
-            .send()
+        let message: Value = res
+            .json()
             .await
-            .map_err(|_| "Error sending message")?;
+            .map_err(|err| format!("Error parsing followup message:\n\n{err}"))?;
+
+        message["id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| String::from("Followup message without id"))
+    }
+
+    pub async fn edit_followup(
+        &self,
+        message_id: &str,
+        body: InteractionCallbackData,
+    ) -> Result<(), String> {
+        self.webhook(
+            reqwest::Method::PATCH,
+            &format!("messages/{message_id}"),
+            Some(&body),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn delete_followup(&self, message_id: &str) -> Result<(), String> {
+        self.webhook::<()>(
+            reqwest::Method::DELETE,
+            &format!("messages/{message_id}"),
+            None,
+        )
+        .await
+        .map(|_| ())
+    }
 
-        if res.status().is_success() {
-            Ok(())
+    /// Interaction tokens work as a webhook for 15 minutes, no bot token needed.
+    async fn webhook<T: Serialize>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&T>,
+    ) -> Result<reqwest::Response, String> {
+        let (app_id, token) = (&self.application_id, &self.token);
+
+        let path = if path.is_empty() {
+            format!("/webhooks/{app_id}/{token}")
         } else {
-            let status = res.status();
+            format!("/webhooks/{app_id}/{token}/{path}")
+        };
 
-            Err(format!(
-                "Err: {status}, while trying to respond to interaction"
-            ))
-        }
+        rest::required(&self.rest)?
+            .request(method, &path, body)
+            .await
+            .map_err(|err| format!("{err}, while trying to respond to interaction"))
     }
 
     pub fn get_value(&self, name: &str) -> Option<&Value> {
@@ -251,24 +457,231 @@ impl Interaction {
         Some(data.name.as_str())
     }
 
+    pub fn get_custom_id(&self) -> Option<&str> {
+        self.data.as_ref()?.custom_id.as_deref()
+    }
+
+    /// Value of a required option, the error names the option that is missing.
+    fn get_option(&self, name: &str) -> Result<&Value, String> {
+        self.get_value(name)
+            .ok_or_else(|| format!("missing option {name}"))
+    }
+
+    pub fn get_string(&self, name: &str) -> Result<&str, String> {
+        self.get_option(name)?
+            .as_str()
+            .ok_or_else(|| format!("option {name} is not a string"))
+    }
+
+    pub fn get_bool(&self, name: &str) -> Result<bool, String> {
+        self.get_option(name)?
+            .as_bool()
+            .ok_or_else(|| format!("option {name} is not a boolean"))
+    }
+
+    pub fn get_integer(&self, name: &str) -> Result<i64, String> {
+        self.get_option(name)?
+            .as_i64()
+            .ok_or_else(|| format!("option {name} is not an integer"))
+    }
+
+    pub fn get_number(&self, name: &str) -> Result<f64, String> {
+        self.get_option(name)?
+            .as_f64()
+            .ok_or_else(|| format!("option {name} is not a number"))
+    }
+
+    fn get_resolved(&self) -> Result<&ResolvedData, String> {
+        self.data
+            .as_ref()
+            .and_then(|data| data.resolved.as_ref())
+            .ok_or_else(|| String::from("missing resolved data"))
+    }
+
+    /// User picked in a user or mentionable option.
+    pub fn get_user(&self, name: &str) -> Result<&User, String> {
+        let id = self.get_string(name)?;
+
+        self.get_resolved()?
+            .users
+            .get(id)
+            .ok_or_else(|| format!("user of option {name} is not resolved"))
+    }
+
+    /// Guild member of the user picked in a user option, missing when they left the guild.
+    pub fn get_member(&self, name: &str) -> Result<&ResolvedMember, String> {
+        let id = self.get_string(name)?;
+
+        self.get_resolved()?
+            .members
+            .get(id)
+            .ok_or_else(|| format!("member of option {name} is not resolved"))
+    }
+
+    pub fn get_channel(&self, name: &str) -> Result<&ResolvedChannel, String> {
+        let id = self.get_string(name)?;
+
+        self.get_resolved()?
+            .channels
+            .get(id)
+            .ok_or_else(|| format!("channel of option {name} is not resolved"))
+    }
+
+    /// Name of the subcommand group that was used, if the command has any.
+    pub fn get_subcommand_group(&self) -> Option<&str> {
+        let options = self.data.as_ref()?.options.as_ref()?;
+
+        options
+            .iter()
+            .find(|option| option.option_type == OptionType::SubCommandGroup)
+            .map(|option| option.name.as_str())
+    }
+
+    /// Name of the subcommand that was used, also when it is inside a group.
+    pub fn get_subcommand(&self) -> Option<&str> {
+        let mut options = self.data.as_ref()?.options.as_ref()?;
+
+        if let Some(group) = options
+            .iter()
+            .find(|option| option.option_type == OptionType::SubCommandGroup)
+        {
+            options = group.options.as_ref()?;
+        }
+
+        options
+            .iter()
+            .find(|option| option.option_type == OptionType::SubCommand)
+            .map(|option| option.name.as_str())
+    }
+
+    /// Value of a text input from a submitted modal.
+    pub fn get_input(&self, custom_id: &str) -> Option<&str> {
+        self.data
+            .as_ref()?
+            .components
+            .as_ref()?
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .find(|input| input.custom_id == custom_id)
+            .map(|input| input.value.as_str())
+    }
+
+    /// The option being typed into and its current value.
+    pub fn get_focused(&self) -> Option<(&str, &Value)> {
+        let options = self.data.as_ref()?.options.as_ref()?;
+
+        rec_focused(options)
+    }
+
+    pub fn get_component_type(&self) -> Option<ComponentType> {
+        self.data.as_ref()?.component_type
+    }
+
+    /// Selected values of a select menu, ids for user and channel selects.
+    pub fn get_values(&self) -> &[String] {
+        self.data
+            .as_ref()
+            .and_then(|d| d.values.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Locale to respond in, the member's own before the guild's one.
+    pub fn get_locale(&self) -> &str {
+        self.locale
+            .as_deref()
+            .or(self.guild_locale.as_deref())
+            .unwrap_or_else(|| Locales::get().default_locale())
+    }
+
+    /// Message `key` from the catalog of the interaction's locale.
+    pub fn text(&self, key: &str) -> String {
+        self.text_with(key, &[])
+    }
+
+    pub fn text_with(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        Locales::get().text(self.get_locale(), key, args)
+    }
+
     pub fn update_voice(mut self, voice: Arc<VoiceState>) -> Self {
         self.member.voice = Some(voice);
         self
     }
+
+    pub fn set_rest(mut self, rest: Arc<Rest>) -> Self {
+        self.rest = Some(rest);
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct InteractionData {
+    #[serde(default)]
     pub id: String,
+    #[serde(default)]
     pub name: String,
     pub options: Option<Vec<InteractionDataOption>>,
+    pub custom_id: Option<String>,
+    pub component_type: Option<ComponentType>,
+    pub values: Option<Vec<String>>,
+    /// Text inputs of a submitted modal.
+    pub components: Option<Vec<SubmittedRow>>,
+    /// Users, members and channels picked in the options, keyed by their id.
+    pub resolved: Option<ResolvedData>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ResolvedData {
+    #[serde(default)]
+    pub users: HashMap<String, User>,
+    #[serde(default)]
+    pub members: HashMap<String, ResolvedMember>,
+    #[serde(default)]
+    pub channels: HashMap<String, ResolvedChannel>,
+}
+
+/// Member without its user, which is in `ResolvedData::users` under the same id.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolvedMember {
+    pub avatar: Option<String>,
+    pub nick: Option<String>,
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub permissions: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolvedChannel {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub channel_type: u32,
+    #[serde(default)]
+    pub permissions: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubmittedRow {
+    pub components: Vec<SubmittedInput>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubmittedInput {
+    pub custom_id: String,
+    #[serde(default)]
+    pub value: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct InteractionDataOption {
     pub name: String,
+    #[serde(rename = "type")]
+    pub option_type: OptionType,
     pub value: Option<Value>,
     pub options: Option<Vec<InteractionDataOption>>,
+    /// Set on the option the user is typing into, only in autocomplete interactions.
+    #[serde(default)]
+    pub focused: bool,
 }
 
 impl InteractionDataOption {
@@ -278,10 +691,10 @@ impl InteractionDataOption {
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct InteractionCallback {
+struct InteractionCallback<T> {
     #[serde(rename = "type")]
     interaction_type: u32,
-    data: InteractionCallbackData,
+    data: T,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -289,16 +702,24 @@ pub struct InteractionCallbackData {
     pub tts: bool,
     pub content: String,
     pub embeds: Vec<Embed>,
+    pub components: Vec<ActionRow>,
     pub allowed_mentions: Option<AllowedMentions>,
     pub flags: Option<u32>,
 }
 
+impl Default for InteractionCallbackData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl InteractionCallbackData {
     pub fn new() -> Self {
         Self {
             tts: false,
             content: String::new(),
             embeds: Vec::new(),
+            components: Vec::new(),
             allowed_mentions: None,
             flags: None,
         }
@@ -318,6 +739,35 @@ impl InteractionCallbackData {
         self.flags = Some(flags);
         self
     }
+
+    pub fn add_component(mut self, row: ActionRow) -> Self {
+        self.components.push(row);
+        self
+    }
+}
+
+/// A popup form, can only be sent as the first response to an interaction.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Modal {
+    pub custom_id: String,
+    pub title: String,
+    pub components: Vec<ActionRow>,
+}
+
+impl Modal {
+    pub fn new(custom_id: String, title: &str) -> Self {
+        Self {
+            custom_id,
+            title: String::from(title),
+            components: Vec::new(),
+        }
+    }
+
+    /// Every text input gets its own row, up to 5 of them.
+    pub fn add_text_input(mut self, input: TextInput) -> Self {
+        self.components.push(ActionRow::new().add_text_input(input));
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -343,6 +793,12 @@ pub struct Embed {
     pub fields: Option<Vec<EmbedField>>,
 }
 
+impl Default for Embed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Embed {
     pub fn new() -> Self {
         Self {
@@ -465,3 +921,63 @@ pub struct EmbedField {
     pub value: String,
     pub inline: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{rec_options, Interaction};
+
+    /// `/queue track move from:3 to:1 note:...`, a subcommand inside a group.
+    fn interaction() -> Interaction {
+        serde_json::from_str(include_str!(
+            "../../tests/fixtures/nested_options_interaction.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn subcommand_inside_a_group() {
+        let interaction = interaction();
+
+        assert_eq!(interaction.get_subcommand_group(), Some("track"));
+        assert_eq!(interaction.get_subcommand(), Some("move"));
+    }
+
+    #[test]
+    fn subcommand_without_a_group() {
+        let mut interaction = interaction();
+
+        let options = interaction.data.as_mut().unwrap().options.as_mut().unwrap();
+
+        let group = options.remove(0);
+
+        options.extend(group.options.unwrap());
+
+        assert_eq!(interaction.get_subcommand_group(), None);
+        assert_eq!(interaction.get_subcommand(), Some("move"));
+        assert_eq!(interaction.get_integer("from"), Ok(3));
+    }
+
+    #[test]
+    fn integers_of_a_nested_subcommand() {
+        let interaction = interaction();
+
+        assert_eq!(interaction.get_integer("from"), Ok(3));
+        assert_eq!(interaction.get_integer("to"), Ok(1));
+        assert!(interaction.get_integer("note").is_err());
+        assert!(interaction.get_integer("missing").is_err());
+    }
+
+    #[test]
+    fn rec_options_skips_subcommands_and_groups() {
+        let interaction = interaction();
+
+        let options = interaction.data.as_ref().unwrap().options.as_ref().unwrap();
+
+        assert_eq!(
+            rec_options(options, "note").and_then(|value| value.as_str()),
+            Some("to the front")
+        );
+        assert!(rec_options(options, "track").is_none());
+        assert!(rec_options(options, "move").is_none());
+    }
+}
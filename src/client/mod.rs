@@ -1,22 +1,28 @@
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use event_loop::DiscordEvLoop;
 use events::Event;
+use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Value};
 use tokio::sync::mpsc::{self, UnboundedSender};
 
 use crate::client::events::InteractionCallbackData;
-use crate::commands::{builder::ApplicationCommand, command_handler};
+use crate::commands::{builder::ApplicationCommand, command_handler, update_panel};
+use crate::settings::Settings;
 
-use self::event_loop::{LavalinkEvLoop, ResumeProperties};
-use self::events::{VoiceServer, VoiceState};
+use self::event_loop::{LavalinkEvLoop, LavalinkPayload, NodeConnection, ResumeProperties};
+use self::events::{Member, NodeStats, PlayerState, TrackEndReason, VoiceServer, VoiceState};
+use self::rest::Rest;
 
+pub mod components;
 mod event_handler;
 mod event_loop;
 pub mod events;
+pub mod rest;
 
 pub struct Client {
     socket: DiscordEvLoop,
@@ -24,6 +30,7 @@ pub struct Client {
     manager: LavalinkClient,
     voice_states: Vec<Arc<VoiceState>>,
     user: String,
+    rest: Arc<Rest>,
 }
 
 pub struct ClientBuilderOptions {
@@ -33,11 +40,17 @@ pub struct ClientBuilderOptions {
 }
 
 impl Client {
-    pub fn new(options: ClientBuilderOptions, lavalink_options: LavalinkBuilderOptions) -> Self {
+    pub fn new(
+        options: ClientBuilderOptions,
+        lavalink_options: Vec<LavalinkBuilderOptions>,
+    ) -> Self {
         let ws_stream = DiscordEvLoop::new();
 
+        let rest = Arc::new(Rest::new(options.token.clone()));
+
         Self {
             socket: ws_stream,
+            rest,
             options,
             manager: LavalinkClient::new(lavalink_options),
             voice_states: Vec::new(),
@@ -76,6 +89,9 @@ impl Client {
             .map_err(|_| "Error sending login payload")?;
 
         self.manager.tx = Some(Arc::clone(&tx));
+        self.manager.rest = Some(Arc::clone(&self.rest));
+
+        self.manager.settings = Settings::load().await;
 
         self.manager.connect().await?;
 
@@ -127,14 +143,28 @@ impl Client {
                         .map_err(|_| "Error sending login payload")?;
                 }
 
-                Event::LavalinkClosed => {
-                    println!("Lavalink connection closed, attempting to resume");
+                Event::LavalinkClosed(name) => {
+                    println!("Lavalink node {name} closed, attempting to resume");
+
+                    self.manager.node_closed(&name)?;
+                }
+
+                Event::LavalinkReconnect(name) => {
+                    self.manager.reconnect_node(&name)?;
+                }
 
-                    self.manager.socket.abort_tasks();
+                Event::LavalinkConnected(name, connection) => {
+                    self.manager.node_connection(&name, *connection)?;
+                }
 
-                    self.manager.connect().await?;
+                Event::LavalinkStats(name, stats) => {
+                    self.manager.update_stats(&name, stats);
+                }
 
-                    self.manager.update_player_tx();
+                Event::PlayerUpdate(guild_id, state) => {
+                    if let Some(player) = self.manager.get_player_mut(&guild_id) {
+                        player.update_state(state);
+                    }
                 }
 
                 Event::Ready(user) => {
@@ -204,34 +234,34 @@ impl Client {
                                 && state.user_id == voice_state.user_id
                         });
 
-                        if let Some(_) = self.voice_states.iter().find(|state| {
+                        let bot_in_guild = self.voice_states.iter().any(|state| {
                             state.guild_id == voice_state.guild_id && state.user_id == self.user
-                        }) {
-                            if prev_voice_state.is_some()
-                                && self
-                                    .voice_states
-                                    .iter()
-                                    .filter(|state| {
-                                        state.guild_id == voice_state.guild_id
-                                            && state.channel_id
-                                                == prev_voice_state.unwrap().channel_id
-                                    })
-                                    .count()
-                                    == 2
-                            {
-                                let tx_c = Arc::clone(&tx);
-                                let guild_id = voice_state.guild_id.clone();
+                        });
+
+                        if bot_in_guild
+                            && prev_voice_state.is_some()
+                            && self
+                                .voice_states
+                                .iter()
+                                .filter(|state| {
+                                    state.guild_id == voice_state.guild_id
+                                        && state.channel_id == prev_voice_state.unwrap().channel_id
+                                })
+                                .count()
+                                == 2
+                        {
+                            let tx_c = Arc::clone(&tx);
+                            let guild_id = voice_state.guild_id.clone();
 
-                                let handle = tokio::spawn(async move {
-                                    tokio::time::sleep(Duration::from_secs(300)).await;
+                            let handle = tokio::spawn(async move {
+                                tokio::time::sleep(Duration::from_secs(300)).await;
 
-                                    if let Err(err) = tx_c.send(Event::DestroyPlayer(guild_id)) {
-                                        println!("Error sending destroy player event: {err}");
-                                    };
-                                });
+                                if let Err(err) = tx_c.send(Event::DestroyPlayer(guild_id)) {
+                                    println!("Error sending destroy player event: {err}");
+                                };
+                            });
 
-                                players_to_destroy.push((voice_state.guild_id.clone(), handle));
-                            }
+                            players_to_destroy.push((voice_state.guild_id.clone(), handle));
                         }
 
                         self.voice_states.retain(|state| {
@@ -245,10 +275,12 @@ impl Client {
                         // self.voice_states
                         //     .remove(&(voice_state.guild_id, voice_state.user_id));
                     } else {
-                        if let Some(_) = self.voice_states.iter().find(|state| {
+                        let bot_in_guild = self.voice_states.iter().any(|state| {
                             state.guild_id == voice_state.guild_id && state.user_id == self.user
-                        }) {
-                            if self
+                        });
+
+                        if bot_in_guild
+                            && self
                                 .voice_states
                                 .iter()
                                 .filter(|state| {
@@ -257,16 +289,15 @@ impl Client {
                                 })
                                 .count()
                                 == 1
+                        {
+                            if let Some((_, handle)) = players_to_destroy
+                                .iter()
+                                .find(|(guild_id, _)| *guild_id == voice_state.guild_id)
                             {
-                                if let Some((_, handle)) = players_to_destroy
-                                    .iter()
-                                    .find(|(guild_id, _)| *guild_id == voice_state.guild_id)
-                                {
-                                    handle.abort();
-
-                                    players_to_destroy
-                                        .retain(|(guild_id, _)| *guild_id != voice_state.guild_id);
-                                }
+                                handle.abort();
+
+                                players_to_destroy
+                                    .retain(|(guild_id, _)| *guild_id != voice_state.guild_id);
                             }
                         }
 
@@ -320,27 +351,70 @@ impl Client {
                     };
                 }
 
-                Event::TrackEnd(guild_id) => {
-                    if let Some(player) = self.manager.get_player_mut(&guild_id) {
-                        player.queue.remove(0);
+                Event::TrackEnd(guild_id, reason) => {
+                    if !reason.may_start_next() {
+                        continue;
+                    }
 
-                        if !player.queue.is_empty() {
-                            let track = &player.queue[0];
+                    if let Some(player) = self.manager.get_player_mut(&guild_id) {
+                        player.advance(reason);
 
-                            player.send(
-                                json!({
-                                    "op": "play",
-                                    "guildId": guild_id,
-                                    "track": track.track
-                                })
-                                .to_string(),
-                            );
-                        } else {
-                            player.playing = false;
+                        if !player.playing {
+                            if let Err(err) = update_panel(&mut self.manager, &guild_id).await {
+                                println!("Error updating player panel: {err:?}");
+                            }
                         }
                     }
                 }
 
+                Event::TrackStart(guild_id) => {
+                    if let Some(player) = self.manager.get_player_mut(&guild_id) {
+                        player.playing = true;
+                        player.set_position(0);
+                    }
+
+                    if let Err(err) = update_panel(&mut self.manager, &guild_id).await {
+                        println!("Error updating player panel: {err:?}");
+                    }
+                }
+
+                // lavalink follows an exception with a TrackEndEvent, so the queue moves on there
+                Event::TrackException(guild_id, exception) => {
+                    let (severity, cause) = (exception.severity, exception.cause);
+                    let message = exception.message.unwrap_or_default();
+
+                    println!("Track exception in {guild_id} ({severity}): {message} {cause}");
+                }
+
+                Event::TrackStuck(guild_id, threshold) => {
+                    println!("Track stuck in {guild_id} for {threshold}ms, skipping");
+
+                    if let Some(player) = self.manager.get_player_mut(&guild_id) {
+                        player.skip();
+                    }
+                }
+
+                Event::WebSocketClosed(guild_id, closed) => {
+                    let (code, reason) = (closed.code, closed.reason);
+
+                    println!("Voice websocket closed in {guild_id}: {code} {reason}");
+
+                    if let Err(err) = self.manager.reconnect_voice(&guild_id, code) {
+                        println!("Error reconnecting to voice channel: {err:?}");
+                    }
+
+                    // the player may be gone already, nothing left for a pending destroy
+                    if self.manager.get_player(&guild_id).is_none() {
+                        players_to_destroy.retain(|(id, handle)| {
+                            if *id == guild_id {
+                                handle.abort();
+                            }
+
+                            *id != guild_id
+                        });
+                    }
+                }
+
                 Event::InteractionCreate(interaction) => {
                     if let Some(voice_state) = /*self.voice_states.get(&(
                             interaction.guild_id.to_string(),
@@ -351,33 +425,20 @@ impl Client {
                                 && state.user_id == interaction.member.user.id
                         })
                     {
-                        let interaction = interaction.update_voice(Arc::clone(voice_state));
+                        let interaction = interaction
+                            .set_rest(Arc::clone(&self.rest))
+                            .update_voice(Arc::clone(voice_state));
 
                         if let Err(err) = command_handler(&interaction, &mut self.manager).await {
-                            println!("Error handling command: {err:?}");
-
-                            if let Err(err) = interaction
-                                .create_message(
-                                    InteractionCallbackData::new().set_content("něco se pokazilo"),
-                                )
-                                .await
-                            {
-                                println!("Error sending message: {err:?}");
-                            }
+                            err.report(&interaction).await;
                         };
-                    } else if let Err(err) = command_handler(&interaction, &mut self.manager).await
-                    {
-                        println!("Error handling command: {err:?}");
+                    } else {
+                        let interaction = interaction.set_rest(Arc::clone(&self.rest));
 
-                        if let Err(err) = interaction
-                            .create_message(
-                                InteractionCallbackData::new().set_content("něco se pokazilo"),
-                            )
-                            .await
-                        {
-                            println!("Error sending message: {err:?}");
-                        }
-                    };
+                        if let Err(err) = command_handler(&interaction, &mut self.manager).await {
+                            err.report(&interaction).await;
+                        };
+                    }
                 }
 
                 Event::DestroyPlayer(guild_id) => {
@@ -385,7 +446,7 @@ impl Client {
                         println!("Error destroying player: {err:?}");
                     };
 
-                    players_to_destroy.retain(|(guild_id, _)| guild_id != guild_id);
+                    players_to_destroy.retain(|(id, _)| *id != guild_id);
                 }
             }
         }
@@ -394,29 +455,16 @@ impl Client {
     }
 
     pub async fn add_command(&self, command: ApplicationCommand) -> Result<(), String> {
-        let app_id = &self.options.app_id;
-
-        let url = format!("https://discord.com/api/v10/applications/{app_id}/commands");
+        command.validate()?;
 
-        let client = reqwest::Client::new();
-
-        let token = &self.options.token;
+        let app_id = &self.options.app_id;
 
-        let res = client
-            .post(url)
-            .header("Authorization", format!("Bot {token}"))
-            .json(&command)
-            .send()
+        self.rest
+            .post(&format!("/applications/{app_id}/commands"), &command)
             .await
-            .map_err(|_| "Error adding command")?;
+            .map_err(|err| format!("{err}, while adding command {command:#?}"))?;
 
-        if res.status().is_success() {
-            Ok(())
-        } else {
-            let status = res.status();
-
-            Err(format!("Err: {status}, while adding command {command:#?}"))
-        }
+        Ok(())
     }
 
     pub async fn add_guild_command(
@@ -424,66 +472,360 @@ impl Client {
         guild_id: String,
         command: ApplicationCommand,
     ) -> Result<(), String> {
+        command.validate()?;
+
         let app_id = &self.options.app_id;
 
-        let url =
-            format!("https://discord.com/api/v10/applications/{app_id}/guilds/{guild_id}/commands");
+        self.rest
+            .post(
+                &format!("/applications/{app_id}/guilds/{guild_id}/commands"),
+                &command,
+            )
+            .await
+            .map_err(|err| format!("{err}, while adding command {command:#?}"))?;
+
+        Ok(())
+    }
+
+    fn commands_path(&self, guild_id: Option<&str>) -> String {
+        let app_id = &self.options.app_id;
 
-        let client = reqwest::Client::new();
+        match guild_id {
+            Some(guild_id) => format!("/applications/{app_id}/guilds/{guild_id}/commands"),
+            None => format!("/applications/{app_id}/commands"),
+        }
+    }
 
-        let token = &self.options.token;
+    /// Commands as Discord has them registered, global without `guild_id`.
+    /// Registered commands including every localization, without the flag Discord
+    /// leaves them out and no command would ever match its local definition.
+    pub async fn registered_commands(&self, guild_id: Option<&str>) -> Result<Vec<Value>, String> {
+        let path = self.commands_path(guild_id);
 
-        let res = client
-            .post(url)
-            .header("Authorization", format!("Bot {token}"))
-            .json(&command)
-            .send()
+        self.rest
+            .get(&format!("{path}?with_localizations=true"))
+            .await?
+            .json()
             .await
-            .map_err(|_| "Error adding command")?;
+            .map_err(|err| format!("Error parsing registered commands:\n\n{err}"))
+    }
 
-        if res.status().is_success() {
-            Ok(())
-        } else {
-            let status = res.status();
+    pub async fn delete_command(
+        &self,
+        guild_id: Option<&str>,
+        command_id: &str,
+    ) -> Result<(), String> {
+        let path = self.commands_path(guild_id);
+
+        self.rest
+            .delete(&format!("{path}/{command_id}"))
+            .await
+            .map_err(|err| format!("{err}, while deleting command {command_id}"))?;
 
-            Err(format!("Err: {status}, while adding command {command:#?}"))
+        Ok(())
+    }
+
+    /// Overwrites the registered commands with nothing.
+    pub async fn delete_all_commands(&self, guild_id: Option<&str>) -> Result<(), String> {
+        self.rest
+            .put(
+                &self.commands_path(guild_id),
+                &Vec::<ApplicationCommand>::new(),
+            )
+            .await
+            .map_err(|err| format!("{err}, while deleting all commands"))?;
+
+        Ok(())
+    }
+
+    /// Makes the registered commands (global without `guild_id`) match `commands`.
+    /// Everything is sent in one bulk overwrite, which also removes the stale ones,
+    /// and only when something actually changed. `dry_run` just reports the diff.
+    pub async fn sync_commands(
+        &self,
+        guild_id: Option<&str>,
+        commands: &[ApplicationCommand],
+        dry_run: bool,
+    ) -> Result<SyncReport, String> {
+        for command in commands {
+            command.validate()?;
+        }
+
+        let registered = self.registered_commands(guild_id).await?;
+
+        let mut report = SyncReport::default();
+
+        for command in commands {
+            let local = normalize_command(
+                &serde_json::to_value(command)
+                    .map_err(|err| format!("Error serializing command:\n\n{err}"))?,
+            );
+
+            match registered
+                .iter()
+                .find(|c| c["name"] == command.name.as_str())
+            {
+                Some(remote) if normalize_command(remote) == local => {
+                    report.unchanged.push(command.name.clone())
+                }
+                Some(_) => report.updated.push(command.name.clone()),
+                None => report.created.push(command.name.clone()),
+            }
+        }
+
+        report.removed = registered
+            .iter()
+            .filter_map(|c| c["name"].as_str())
+            .filter(|name| !commands.iter().any(|c| c.name == *name))
+            .map(String::from)
+            .collect();
+
+        if dry_run || !report.has_changes() {
+            return Ok(report);
         }
+
+        self.rest
+            .put(&self.commands_path(guild_id), &commands)
+            .await
+            .map_err(|err| format!("{err}, while overwriting commands"))?;
+
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+impl SyncReport {
+    pub fn has_changes(&self) -> bool {
+        !(self.created.is_empty() && self.updated.is_empty() && self.removed.is_empty())
+    }
+}
+
+/// Fields Discord adds to registered commands, they are never part of our definitions.
+const SERVER_FIELDS: [&str; 12] = [
+    "id",
+    "application_id",
+    "guild_id",
+    "version",
+    "default_member_permissions",
+    "default_permission",
+    "dm_permission",
+    "nsfw",
+    "contexts",
+    "integration_types",
+    "name_localized",
+    "description_localized",
+];
+
+/// Strips what makes a registered command look different from the same local
+/// definition: server side fields, nulls, `false` and empty lists, which are all
+/// just the defaults.
+fn normalize_command(command: &Value) -> Value {
+    match command {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(key, _)| !SERVER_FIELDS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), normalize_command(value)))
+                .filter(|(_, value)| match value {
+                    Value::Null | Value::Bool(false) => false,
+                    Value::Array(values) => !values.is_empty(),
+                    Value::Object(map) => !map.is_empty(),
+                    _ => true,
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(normalize_command).collect()),
+        value => value.clone(),
     }
 }
 
 #[derive(Debug)]
 pub struct LavalinkClient {
-    socket: LavalinkEvLoop,
+    nodes: Vec<LavalinkNode>,
     tx: Option<Arc<UnboundedSender<Event>>>,
     voice_servers: Vec<Rc<VoiceServer>>,
     voice_states: Vec<Arc<VoiceState>>,
     players: Vec<Player>,
+    searches: Vec<PendingSearch>,
+    suggestions: Vec<Suggestions>,
+    suggested_at: HashMap<String, (Instant, String)>,
+    rest: Option<Arc<Rest>>,
+    pub settings: Settings,
+}
+
+/// Discord's error code for a message that was deleted or never existed.
+const UNKNOWN_MESSAGE: u32 = 10008;
+
+/// How long the results of `/search` can be picked from.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How long autocomplete results are reused for the same query.
+const SUGGESTIONS_TTL: Duration = Duration::from_secs(60);
+/// Minimum time between two autocomplete searches of one user, every keystroke
+/// sends an interaction so most of them get answered from the cache.
+const SUGGESTIONS_INTERVAL: Duration = Duration::from_millis(750);
+
+#[derive(Debug)]
+struct Suggestions {
+    key: String,
+    tracks: Vec<Track>,
+    created_at: Instant,
+}
+
+#[derive(Debug)]
+pub struct PendingSearch {
+    pub id: String,
+    pub user_id: String,
+    pub tracks: Vec<Track>,
+    created_at: Instant,
 }
 
 #[derive(Debug)]
 pub struct LavalinkBuilderOptions {
+    pub name: String,
     pub host: String,
     pub port: u16,
     pub password: String,
+    pub version: LavalinkVersion,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LavalinkVersion {
+    #[default]
+    V3,
+    V4,
+}
+
+async fn load_tracks(
+    options: &LavalinkBuilderOptions,
+    query: &str,
+    platform: Option<&str>,
+) -> Result<SearchResult, String> {
+    let query_string = match platform {
+        Some(platform) => format!("{platform}:{query}"),
+        None => query.to_string(),
+    };
+
+    let client = reqwest::Client::new();
+
+    let (host, port) = (&options.host, options.port);
+
+    let url = match options.version {
+        LavalinkVersion::V3 => format!("http://{host}:{port}/loadtracks"),
+        LavalinkVersion::V4 => format!("http://{host}:{port}/v4/loadtracks"),
+    };
+
+    let res = client
+        .get(url)
+        .query(&[("identifier", query_string)])
+        .header("Authorization", &options.password)
+        .send()
+        .await
+        .map_err(|_| "Error searching")?;
+
+    match options.version {
+        LavalinkVersion::V3 => res
+            .json()
+            .await
+            .map_err(|e| format!("Error parsing search result: {e:?}")),
+        LavalinkVersion::V4 => res
+            .json::<SearchResultV4>()
+            .await
+            .map_err(|e| format!("Error parsing search result: {e:?}"))?
+            .into_search_result(),
+    }
+}
+
+#[derive(Debug)]
+pub struct LavalinkNode {
+    socket: LavalinkEvLoop,
+    pub stats: Option<NodeStats>,
+    pub available: bool,
+    /// A connection attempt is running in the background.
+    connecting: bool,
+}
+
+impl LavalinkNode {
+    pub fn name(&self) -> &str {
+        &self.socket.options.name
+    }
+
+    pub fn version(&self) -> LavalinkVersion {
+        self.socket.options.version
+    }
+
+    /// Stats only arrive once a minute, so when we know of more players on the
+    /// node than it reported, the ones it doesn't know of yet are added.
+    fn penalty(&self, players: usize) -> f64 {
+        match &self.stats {
+            Some(stats) => {
+                let unreported = (players as u32).saturating_sub(stats.playing_players);
+
+                stats.penalty() + unreported as f64
+            }
+            None => players as f64,
+        }
+    }
 }
 
 impl LavalinkClient {
-    pub fn new(options: LavalinkBuilderOptions) -> LavalinkClient {
-        let ws_stream = LavalinkEvLoop::new(options);
+    pub fn new(options: Vec<LavalinkBuilderOptions>) -> LavalinkClient {
+        let nodes = options
+            .into_iter()
+            .map(|options| LavalinkNode {
+                socket: LavalinkEvLoop::new(options),
+                stats: None,
+                available: false,
+                connecting: false,
+            })
+            .collect();
 
         LavalinkClient {
-            socket: ws_stream,
+            nodes,
             tx: None,
             voice_servers: Vec::new(),
             voice_states: Vec::new(),
             players: vec![],
+            searches: vec![],
+            suggestions: vec![],
+            suggested_at: HashMap::new(),
+            rest: None,
+            settings: Settings::default(),
         }
     }
 
     pub async fn connect(&mut self) -> Result<(), String> {
         let tx = self.tx.as_ref().ok_or("missing sender")?;
 
-        self.socket.connect(Arc::clone(tx)).await
+        let mut failed = vec![];
+
+        for node in &mut self.nodes {
+            match node.socket.connect(Arc::clone(tx)).await {
+                Ok(_) => node.available = true,
+                Err(err) => {
+                    println!("lavalink {}: {err}", node.name());
+
+                    failed.push(String::from(node.name()));
+                }
+            }
+        }
+
+        for name in failed {
+            self.schedule_reconnect(name);
+        }
+
+        if !self.nodes.iter().any(|node| node.available) {
+            return Err(String::from("Failed to connect to any lavalink node"));
+        }
+
+        Ok(())
     }
 
     pub fn join(&mut self, guild_id: &String, channel_id: &String) -> Result<&mut Player, String> {
@@ -491,16 +833,21 @@ impl LavalinkClient {
             return Err(format!("Already in a voice channel in {guild_id}"));
         }
 
-        self.send_ws(guild_id, Some(channel_id.to_string()))?;
+        let node = self.best_node().ok_or("No lavalink node available")?;
 
-        let sender = self.socket.sender.as_ref().ok_or("missing sender")?;
+        let sender = node.socket.sender.as_ref().ok_or("missing sender")?;
 
-        self.players.push(Player::new(
+        let player = Player::new(
             guild_id.to_string(),
             channel_id.to_string(),
+            self.settings.guild(guild_id).volume,
             Arc::clone(sender),
-            Rc::clone(&self.socket.options),
-        ));
+            Rc::clone(&node.socket.options),
+        );
+
+        self.send_ws(guild_id, Some(channel_id.to_string()))?;
+
+        self.players.push(player);
 
         let player = self.get_player_mut(guild_id).ok_or("Player not found")?;
 
@@ -512,13 +859,7 @@ impl LavalinkClient {
 
         let player = self.get_player(guild_id).ok_or("Player not found")?;
 
-        player.send(
-            json!({
-                "op": "destroy",
-                "guildId": guild_id
-            })
-            .to_string(),
-        );
+        player.destroy();
 
         self.players.retain(|p| p.guild_id != *guild_id);
 
@@ -557,189 +898,1475 @@ impl LavalinkClient {
             .find(|player| player.guild_id == *guild_id)
     }
 
-    fn attempt_connection(&self, guild_id: &str) -> Result<(), String> {
-        let server = match /*self.voice_servers.get(&guild_id)*/self.voice_servers.iter().find(|s| s.guild_id == guild_id) {
-            Some(server) => server,
-            None => return Err(String::from("No voice server found")),
-        };
+    /// 4014 means we were disconnected from the channel, which the voice state
+    /// update takes care of. 4015 is a crashed voice server that only needs the
+    /// same session again, an abnormal close (1006), an invalid session (4006) and
+    /// a session timeout (4009) need a fresh voice session. Reconnecting won't
+    /// help with any other code, so the player is destroyed.
+    fn reconnect_voice(&mut self, guild_id: &str, code: u16) -> Result<(), String> {
+        let player = self.get_player(guild_id).ok_or("Player not found")?;
 
-        let session = match /*self.voice_states.get(&guild_id)*/self.voice_states.iter().find(|s| s.guild_id == guild_id) {
-            Some(session) => &session.session_id,
-            None => return Err(String::from("No voice state found")),
-        };
+        match code {
+            4014 => Ok(()),
+            4015 => self.attempt_connection(guild_id),
+            1006 | 4006 | 4009 => self.send_ws(&player.guild_id, Some(player.channel_id.clone())),
+            _ => {
+                println!("Voice connection in {guild_id} can't be restored, leaving");
 
-        let player = match self.get_player(&guild_id) {
-            Some(player) => player,
-            None => return Err(String::from("No player found")),
-        };
+                self.destroy_player(&String::from(guild_id))
+            }
+        }
+    }
 
-        player.connect(session, server);
+    /// Searches on the least loaded node, for when there is no player yet.
+    pub async fn search(
+        &self,
+        query: &str,
+        platform: Option<&str>,
+    ) -> Result<SearchResult, String> {
+        let node = self.best_node().ok_or("No lavalink node available")?;
 
-        Ok(())
+        load_tracks(&node.socket.options, query, platform).await
     }
 
-    fn update_player_tx(&mut self) {
-        if let Some(sender) = &self.socket.sender {
-            for player in &mut self.players {
-                player.tx = Arc::clone(sender);
-            }
-        }
+    /// Sends a message to a text channel as the bot, returns the id of the new message.
+    pub async fn send_message(
+        &self,
+        channel_id: &str,
+        data: &InteractionCallbackData,
+    ) -> Result<String, String> {
+        let message: Value = rest::required(&self.rest)?
+            .post(&format!("/channels/{channel_id}/messages"), data)
+            .await
+            .map_err(|err| format!("Error sending message:\n\n{err}"))?
+            .json()
+            .await
+            .map_err(|err| format!("Error parsing message:\n\n{err}"))?;
+
+        message["id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| String::from("Message without id"))
     }
-}
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct SearchResult {
-    #[serde(rename = "loadType")]
-    pub load_type: String,
-    #[serde(rename = "playlistInfo")]
-    pub playlist_info: Option<PlaylistInfo>,
-    pub tracks: Vec<Track>,
-}
+    /// Edits a message of the bot, `Ok(false)` when the message doesn't exist anymore.
+    pub async fn edit_message(
+        &self,
+        channel_id: &str,
+        message_id: &str,
+        data: &InteractionCallbackData,
+    ) -> Result<bool, String> {
+        let res = rest::required(&self.rest)?
+            .send(
+                reqwest::Method::PATCH,
+                &format!("/channels/{channel_id}/messages/{message_id}"),
+                Some(data),
+            )
+            .await
+            .map_err(|err| format!("Error editing message:\n\n{err}"))?;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct Track {
-    pub track: String,
-    pub info: TrackInfo,
-}
+        let status = res.status();
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct TrackInfo {
-    pub identifier: String,
-    pub author: String,
-    pub length: u64,
-    pub position: u64,
-    pub title: String,
-    pub uri: String,
-}
+        if status.is_success() {
+            return Ok(true);
+        }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct PlaylistInfo {
-    pub name: Option<String>,
-    #[serde(rename = "selectedTrack")]
-    pub selected_track: Option<i32>,
-}
+        let error: Value = res.json().await.unwrap_or_default();
 
-#[derive(Debug)]
-pub struct Player {
-    pub guild_id: String,
-    pub channel_id: String,
-    pub paused: bool,
-    pub volume: u8,
-    pub playing: bool,
-    pub queue: Vec<Track>,
-    tx: Arc<UnboundedSender<String>>,
-    options: Rc<LavalinkBuilderOptions>,
-}
+        if status == reqwest::StatusCode::NOT_FOUND && error["code"] == UNKNOWN_MESSAGE {
+            return Ok(false);
+        }
 
-impl Player {
-    pub fn new(
+        Err(format!("Error editing message:\n\nErr: {status}, {error}"))
+    }
+
+    /// Search results for autocomplete. Cached per query, and while the user keeps
+    /// typing faster than [`SUGGESTIONS_INTERVAL`] the last results are returned instead.
+    pub async fn suggest(
+        &mut self,
+        user_id: &str,
+        query: &str,
+        platform: &str,
+    ) -> Result<Vec<Track>, String> {
+        let key = format!("{platform}:{}", query.trim().to_lowercase());
+
+        self.suggestions
+            .retain(|suggestions| suggestions.created_at.elapsed() < SUGGESTIONS_TTL);
+
+        let cached = |key: &str| {
+            self.suggestions
+                .iter()
+                .find(|suggestions| suggestions.key == key)
+                .map(|suggestions| suggestions.tracks.clone())
+        };
+
+        if let Some(tracks) = cached(&key) {
+            return Ok(tracks);
+        }
+
+        if let Some((searched_at, last_key)) = self.suggested_at.get(user_id) {
+            if searched_at.elapsed() < SUGGESTIONS_INTERVAL {
+                return Ok(cached(last_key).unwrap_or_default());
+            }
+        }
+
+        self.suggested_at
+            .insert(String::from(user_id), (Instant::now(), key.clone()));
+
+        self.suggested_at
+            .retain(|_, (searched_at, _)| searched_at.elapsed() < SUGGESTIONS_TTL);
+
+        let tracks = self
+            .search(query, Some(platform))
+            .await?
+            .tracks
+            .into_iter()
+            .take(10)
+            .collect::<Vec<_>>();
+
+        self.suggestions.push(Suggestions {
+            key,
+            tracks: tracks.clone(),
+            created_at: Instant::now(),
+        });
+
+        Ok(tracks)
+    }
+
+    /// Remembers search results until the user picks one, drops the expired ones.
+    pub fn add_search(&mut self, id: String, user_id: String, tracks: Vec<Track>) {
+        self.searches
+            .retain(|search| search.created_at.elapsed() < SEARCH_TIMEOUT);
+
+        self.searches.push(PendingSearch {
+            id,
+            user_id,
+            tracks,
+            created_at: Instant::now(),
+        });
+    }
+
+    pub fn take_search(&mut self, id: &str, user_id: &str) -> Option<PendingSearch> {
+        let index = self
+            .searches
+            .iter()
+            .position(|search| search.id == id && search.user_id == user_id)?;
+
+        let search = self.searches.remove(index);
+
+        if search.created_at.elapsed() < SEARCH_TIMEOUT {
+            Some(search)
+        } else {
+            None
+        }
+    }
+
+    /// The available node with the lowest load, based on its last `stats`.
+    pub fn best_node(&self) -> Option<&LavalinkNode> {
+        self.least_loaded(None)
+    }
+
+    /// Only nodes of the same version can take over players, v3 and v4 encode
+    /// tracks differently.
+    fn failover_node(&self, version: LavalinkVersion) -> Option<&LavalinkNode> {
+        self.least_loaded(Some(version))
+    }
+
+    fn least_loaded(&self, version: Option<LavalinkVersion>) -> Option<&LavalinkNode> {
+        self.nodes
+            .iter()
+            .filter(|node| node.available && node.socket.sender.is_some())
+            .filter(|node| version.is_none_or(|version| node.version() == version))
+            .min_by(|a, b| {
+                let a = a.penalty(self.node_players(a.name()));
+                let b = b.penalty(self.node_players(b.name()));
+
+                a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    fn node_players(&self, name: &str) -> usize {
+        self.players.iter().filter(|p| p.node() == name).count()
+    }
+
+    fn update_stats(&mut self, name: &str, stats: NodeStats) {
+        if let Some(node) = self.nodes.iter_mut().find(|node| node.name() == name) {
+            node.stats = Some(stats);
+        }
+    }
+
+    /// Tries to resume the node right away, if that fails its players are moved
+    /// to the healthiest node left and the node is retried later.
+    fn node_closed(&mut self, name: &str) -> Result<(), String> {
+        let node = self.node_mut(name)?;
+
+        node.available = false;
+        node.socket.closed();
+
+        self.spawn_connect(name)
+    }
+
+    fn reconnect_node(&mut self, name: &str) -> Result<(), String> {
+        if self.node_mut(name)?.available {
+            return Ok(());
+        }
+
+        self.spawn_connect(name)
+    }
+
+    /// Connecting can take up to the node timeout, which would hold up every
+    /// guild, so it runs in its own task and the connection comes back as
+    /// `Event::LavalinkConnected`.
+    fn spawn_connect(&mut self, name: &str) -> Result<(), String> {
+        let tx = Arc::clone(self.tx.as_ref().ok_or("missing sender")?);
+
+        let node = self.node_mut(name)?;
+
+        if node.connecting {
+            return Ok(());
+        }
+
+        node.connecting = true;
+        node.socket.abort_tasks();
+
+        let connector = node.socket.connector();
+        let name = String::from(name);
+
+        tokio::spawn(async move {
+            let connection = connector.connect().await;
+
+            if let Err(err) = tx.send(Event::LavalinkConnected(name, Box::new(connection))) {
+                println!("Error sending lavalink connection: {err}");
+            }
+        });
+
+        Ok(())
+    }
+
+    fn node_connection(
+        &mut self,
+        name: &str,
+        connection: Result<NodeConnection, String>,
+    ) -> Result<(), String> {
+        let tx = Arc::clone(self.tx.as_ref().ok_or("missing sender")?);
+
+        let node = self.node_mut(name)?;
+
+        node.connecting = false;
+
+        match connection {
+            Ok(connection) => {
+                println!("lavalink {name}: connected");
+
+                let resumed = node.socket.attach(connection, tx);
+
+                node.available = true;
+
+                self.node_connected(name, resumed);
+            }
+            Err(err) => {
+                println!("lavalink {name}: {err}");
+
+                self.schedule_reconnect(String::from(name));
+
+                self.failover(name);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn node_mut(&mut self, name: &str) -> Result<&mut LavalinkNode, String> {
+        self.nodes
+            .iter_mut()
+            .find(|node| node.name() == name)
+            .ok_or_else(|| String::from("Node not found"))
+    }
+
+    /// Points the node's players at the new connection. Without a resumed session
+    /// lavalink has forgotten them, so they are connected and started again. Players
+    /// still waiting on nodes that are down get moved here too.
+    fn node_connected(&mut self, name: &str, resumed: bool) {
+        self.update_player_tx(name);
+
+        if !resumed {
+            let guild_ids: Vec<String> = self
+                .players
+                .iter()
+                .filter(|p| p.node() == name)
+                .map(|p| p.guild_id.clone())
+                .collect();
+
+            for guild_id in guild_ids {
+                if let Err(err) = self.attempt_connection(&guild_id) {
+                    println!("Error restoring player {guild_id}: {err}");
+                }
+
+                if let Some(player) = self.get_player_mut(&guild_id) {
+                    player.resume();
+                }
+            }
+        }
+
+        let down: Vec<String> = self
+            .nodes
+            .iter()
+            .filter(|node| !node.available)
+            .map(|node| String::from(node.name()))
+            .collect();
+
+        for name in down {
+            self.failover(&name);
+        }
+    }
+
+    fn schedule_reconnect(&self, name: String) {
+        if let Some(tx) = &self.tx {
+            let tx = Arc::clone(tx);
+
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+
+                if let Err(err) = tx.send(Event::LavalinkReconnect(name)) {
+                    println!("Error sending lavalink reconnect event: {err}");
+                }
+            });
+        }
+    }
+
+    fn failover(&mut self, name: &str) {
+        let version = match self.nodes.iter().find(|node| node.name() == name) {
+            Some(node) => node.version(),
+            None => return,
+        };
+
+        let (sender, options) = match self.failover_node(version) {
+            Some(node) => match &node.socket.sender {
+                Some(sender) => (Arc::clone(sender), Rc::clone(&node.socket.options)),
+                None => return,
+            },
+            None => {
+                println!("lavalink {name}: no node of the same version available, players will wait for it");
+
+                return;
+            }
+        };
+
+        let guild_ids: Vec<String> = self
+            .players
+            .iter()
+            .filter(|p| p.node() == name)
+            .map(|p| p.guild_id.clone())
+            .collect();
+
+        for guild_id in guild_ids {
+            if let Some(player) = self.get_player_mut(&guild_id) {
+                player.tx = Arc::clone(&sender);
+                player.options = Rc::clone(&options);
+            }
+
+            if let Err(err) = self.attempt_connection(&guild_id) {
+                println!("Error moving player {guild_id}: {err}");
+            }
+
+            if let Some(player) = self.get_player_mut(&guild_id) {
+                player.resume();
+            }
+        }
+    }
+
+    fn attempt_connection(&self, guild_id: &str) -> Result<(), String> {
+        let server = match /*self.voice_servers.get(&guild_id)*/self.voice_servers.iter().find(|s| s.guild_id == guild_id) {
+            Some(server) => server,
+            None => return Err(String::from("No voice server found")),
+        };
+
+        let session = match /*self.voice_states.get(&guild_id)*/self.voice_states.iter().find(|s| s.guild_id == guild_id) {
+            Some(session) => &session.session_id,
+            None => return Err(String::from("No voice state found")),
+        };
+
+        let player = match self.get_player(guild_id) {
+            Some(player) => player,
+            None => return Err(String::from("No player found")),
+        };
+
+        player.connect(session, server);
+
+        Ok(())
+    }
+
+    fn update_player_tx(&mut self, name: &str) {
+        let node = match self.nodes.iter().find(|node| node.name() == name) {
+            Some(node) => node,
+            None => return,
+        };
+
+        if let Some(sender) = &node.socket.sender {
+            for player in self.players.iter_mut().filter(|p| p.node() == name) {
+                player.tx = Arc::clone(sender);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum LoadType {
+    #[serde(rename = "TRACK_LOADED", alias = "track")]
+    TrackLoaded,
+    #[serde(rename = "PLAYLIST_LOADED", alias = "playlist")]
+    PlaylistLoaded,
+    #[serde(rename = "SEARCH_RESULT", alias = "search")]
+    SearchResult,
+    #[serde(rename = "NO_MATCHES", alias = "empty")]
+    NoMatches,
+    #[serde(rename = "LOAD_FAILED", alias = "error")]
+    LoadFailed,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchResult {
+    #[serde(rename = "loadType")]
+    pub load_type: LoadType,
+    #[serde(rename = "playlistInfo")]
+    pub playlist_info: Option<PlaylistInfo>,
+    pub tracks: Vec<Track>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResultV4 {
+    #[serde(rename = "loadType")]
+    load_type: LoadType,
+    data: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistV4 {
+    info: PlaylistInfo,
+    tracks: Vec<Track>,
+}
+
+impl SearchResultV4 {
+    fn into_search_result(self) -> Result<SearchResult, String> {
+        let err_parse = |e| format!("Error parsing search result: {e:?}");
+
+        let (playlist_info, tracks) = match self.load_type {
+            LoadType::TrackLoaded => (
+                None,
+                vec![serde_json::from_value(self.data).map_err(err_parse)?],
+            ),
+            LoadType::PlaylistLoaded => {
+                let playlist: PlaylistV4 = serde_json::from_value(self.data).map_err(err_parse)?;
+
+                (Some(playlist.info), playlist.tracks)
+            }
+            LoadType::SearchResult => (None, serde_json::from_value(self.data).map_err(err_parse)?),
+            LoadType::NoMatches | LoadType::LoadFailed => (None, vec![]),
+        };
+
+        Ok(SearchResult {
+            load_type: self.load_type,
+            playlist_info,
+            tracks,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Track {
+    #[serde(alias = "encoded")]
+    pub track: String,
+    pub info: TrackInfo,
+    #[serde(skip)]
+    pub requester: Option<Requester>,
+}
+
+impl Track {
+    pub fn set_requester(mut self, member: &Member) -> Self {
+        self.requester = Some(Requester::new(member));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Requester {
+    pub user_id: String,
+    pub name: String,
+    /// Unix timestamp in seconds
+    pub added_at: u64,
+}
+
+impl Requester {
+    pub fn new(member: &Member) -> Self {
+        let added_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            user_id: member.user.id.clone(),
+            name: String::from(member.display_name()),
+            added_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TrackInfo {
+    pub identifier: String,
+    pub author: String,
+    pub length: u64,
+    pub position: u64,
+    pub title: String,
+    /// Not every source has a link, e.g. local files.
+    #[serde(default)]
+    pub uri: Option<String>,
+    #[serde(rename = "isStream", default)]
+    pub is_stream: bool,
+    #[serde(rename = "isSeekable", default)]
+    pub is_seekable: bool,
+}
+
+impl TrackInfo {
+    /// Markdown link to the track, or just its title when there is no link.
+    pub fn link(&self) -> String {
+        match &self.uri {
+            Some(uri) => format!("[{}]({uri})", self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlaylistInfo {
+    pub name: Option<String>,
+    #[serde(rename = "selectedTrack")]
+    pub selected_track: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopMode {
+    #[default]
+    Off,
+    Track,
+    Queue,
+}
+
+#[derive(Debug)]
+pub struct Player {
+    pub guild_id: String,
+    pub channel_id: String,
+    pub paused: bool,
+    pub volume: u8,
+    pub playing: bool,
+    pub queue: Vec<Track>,
+    pub loop_mode: LoopMode,
+    pub state: PlayerState,
+    /// Where the control panel gets posted, the channel the player was first used from.
+    pub text_channel_id: Option<String>,
+    pub panel_message_id: Option<String>,
+    /// Locale of the control panel, taken from whoever first used the player.
+    pub locale: Option<String>,
+    updated_at: Instant,
+    awaiting_update: bool,
+    tx: Arc<UnboundedSender<LavalinkPayload>>,
+    options: Rc<LavalinkBuilderOptions>,
+}
+
+impl Player {
+    pub fn new(
         guild_id: String,
         channel_id: String,
-        tx: Arc<UnboundedSender<String>>,
+        volume: u8,
+        tx: Arc<UnboundedSender<LavalinkPayload>>,
         options: Rc<LavalinkBuilderOptions>,
     ) -> Self {
         Self {
             guild_id,
             channel_id,
             paused: false,
-            volume: 100,
+            volume,
             playing: false,
             queue: vec![],
+            loop_mode: LoopMode::Off,
+            state: PlayerState::default(),
+            text_channel_id: None,
+            panel_message_id: None,
+            locale: None,
+            updated_at: Instant::now(),
+            awaiting_update: false,
             tx,
             options,
         }
     }
 
+    pub fn node(&self) -> &str {
+        &self.options.name
+    }
+
     pub fn connect(&self, session_id: &str, event: &VoiceServer) {
-        self.send(
-            json!({
-                "op": "voiceUpdate",
-                "guildId": self.guild_id,
-                "sessionId": session_id,
-                "event": event
-            })
-            .to_string(),
-        );
+        match self.options.version {
+            LavalinkVersion::V3 => self.send(
+                json!({
+                    "op": "voiceUpdate",
+                    "guildId": self.guild_id,
+                    "sessionId": session_id,
+                    "event": event
+                })
+                .to_string(),
+            ),
+            LavalinkVersion::V4 => self.update(json!({
+                "voice": {
+                    "token": event.token,
+                    "endpoint": event.endpoint,
+                    "sessionId": session_id
+                }
+            })),
+        }
     }
 
     pub fn send(&self, payload: String) {
+        if let Err(err) = self.tx.send(LavalinkPayload::Ws(payload)) {
+            println!("Error sending payload to lavalink: {err}");
+        }
+    }
+
+    pub fn update(&self, body: Value) {
+        let payload = LavalinkPayload::Update {
+            guild_id: self.guild_id.clone(),
+            body,
+        };
+
         if let Err(err) = self.tx.send(payload) {
             println!("Error sending payload to lavalink: {err}");
         }
     }
 
+    pub fn destroy(&self) {
+        match self.options.version {
+            LavalinkVersion::V3 => self.send(
+                json!({
+                    "op": "destroy",
+                    "guildId": self.guild_id
+                })
+                .to_string(),
+            ),
+            LavalinkVersion::V4 => {
+                let payload = LavalinkPayload::Destroy {
+                    guild_id: self.guild_id.clone(),
+                };
+
+                if let Err(err) = self.tx.send(payload) {
+                    println!("Error sending payload to lavalink: {err}");
+                }
+            }
+        }
+    }
+
     pub async fn search(
         &self,
         query: &str,
         platform: Option<&str>,
     ) -> Result<SearchResult, String> {
-        let query_string = match platform {
-            Some(platform) => format!("{platform}:{query}"),
-            None => query.to_string(),
-        };
-
-        let client = reqwest::Client::new();
-
-        let (host, port) = (&self.options.host, self.options.port);
-
-        let url = format!("http://{host}:{port}/loadtracks?identifier={query_string}");
-
-        let res = client
-            .get(url)
-            .header("Authorization", &self.options.password)
-            .send()
-            .await
-            .map_err(|_| "Error searching")?
-            .json()
-            .await
-            .map_err(|e| format!("Error parsing search result: {e:?}"))?;
-
-        Ok(res)
+        load_tracks(&self.options, query, platform).await
     }
 
-    pub fn play(&mut self, track: Track) {
-        if !self.playing {
-            self.playing = true;
+    pub fn send_play(&self, track: &Track) {
+        self.send_play_from(track, 0);
+    }
 
-            self.send(
+    pub fn send_play_from(&self, track: &Track, position: u64) {
+        match self.options.version {
+            LavalinkVersion::V3 => self.send(
                 json!({
                     "op": "play",
                     "guildId": self.guild_id,
-                    "track": track.track
+                    "track": track.track,
+                    "startTime": position,
+                    "pause": self.paused,
+                    "volume": self.volume
                 })
                 .to_string(),
-            );
+            ),
+            LavalinkVersion::V4 => self.update(json!({
+                "track": { "encoded": track.track },
+                "position": position,
+                "paused": self.paused,
+                "volume": self.volume
+            })),
         }
-
-        self.queue.push(track);
     }
 
-    pub fn pause(&self, paused: bool) {
-        self.send(
-            json!({
-                "op": "pause",
-                "guildId": self.guild_id,
-                "pause": paused
-            })
-            .to_string(),
-        );
+    /// Restarts the current track where it left off, used after the player
+    /// was moved to another node.
+    pub fn resume(&mut self) {
+        if !self.playing {
+            return;
+        }
+
+        let position = self.position();
+
+        if let Some(track) = self.queue.first() {
+            self.send_play_from(track, position);
+        }
+
+        self.started(position);
+    }
+
+    pub fn update_state(&mut self, state: PlayerState) {
+        self.state = state;
+        self.updated_at = Instant::now();
+        self.awaiting_update = false;
+    }
+
+    /// Marks a track as just sent from `position`, so it counts as playing
+    /// until lavalink reports its own state for it.
+    fn started(&mut self, position: u64) {
+        self.set_position(position);
+        self.awaiting_update = true;
+    }
+
+    pub fn set_position(&mut self, position: u64) {
+        self.state.position = position;
+        self.updated_at = Instant::now();
+    }
+
+    /// Current playback position in ms, interpolated from the last `playerUpdate`,
+    /// or from when the track was sent if lavalink hasn't reported it yet.
+    /// Lavalink's own `time` is ignored so clock drift between hosts doesn't matter.
+    pub fn position(&self) -> u64 {
+        let mut position = self.state.position;
+
+        if self.playing && !self.paused && (self.state.connected || self.awaiting_update) {
+            position += self.updated_at.elapsed().as_millis() as u64;
+        }
+
+        match self.queue.first() {
+            Some(track) if !track.info.is_stream => position.min(track.info.length),
+            _ => position,
+        }
+    }
+
+    pub fn play(&mut self, track: Track) {
+        if !self.playing {
+            self.playing = true;
+
+            self.send_play(&track);
+            self.started(0);
+        }
+
+        self.queue.push(track);
+    }
+
+    pub fn pause(&mut self, paused: bool) {
+        let position = self.position();
+
+        self.set_position(position);
+
+        self.paused = paused;
+
+        match self.options.version {
+            LavalinkVersion::V3 => self.send(
+                json!({
+                    "op": "pause",
+                    "guildId": self.guild_id,
+                    "pause": paused
+                })
+                .to_string(),
+            ),
+            LavalinkVersion::V4 => self.update(json!({ "paused": paused })),
+        }
+    }
+
+    pub fn seek(&mut self, position: u64) -> Result<(), String> {
+        let track = self.queue.first().ok_or("Nothing is playing")?;
+
+        if !track.info.is_seekable || track.info.is_stream {
+            return Err(String::from("Track is not seekable"));
+        }
+
+        if position >= track.info.length {
+            return Err(format!("Position {position} is past the end of the track"));
+        }
+
+        match self.options.version {
+            LavalinkVersion::V3 => self.send(
+                json!({
+                    "op": "seek",
+                    "guildId": self.guild_id,
+                    "position": position
+                })
+                .to_string(),
+            ),
+            LavalinkVersion::V4 => self.update(json!({ "position": position })),
+        }
+
+        self.set_position(position);
+
+        Ok(())
+    }
+
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = volume;
+
+        match self.options.version {
+            LavalinkVersion::V3 => self.send(
+                json!({
+                    "op": "volume",
+                    "guildId": self.guild_id,
+                    "volume": volume
+                })
+                .to_string(),
+            ),
+            LavalinkVersion::V4 => self.update(json!({ "volume": volume })),
+        }
+    }
+
+    pub fn stop(&self) {
+        match self.options.version {
+            LavalinkVersion::V3 => self.send(
+                json!({
+                    "op": "stop",
+                    "guildId": self.guild_id
+                })
+                .to_string(),
+            ),
+            LavalinkVersion::V4 => self.update(json!({
+                "track": { "encoded": null }
+            })),
+        }
+    }
+
+    /// Moves past the current track according to the loop mode and starts the
+    /// next one. Called when lavalink reports a track end that allows starting
+    /// the next track. A track that failed to load is never looped.
+    pub fn advance(&mut self, reason: TrackEndReason) {
+        if !self.queue.is_empty() {
+            match (self.loop_mode, reason) {
+                (_, TrackEndReason::LoadFailed) | (LoopMode::Off, _) => {
+                    self.queue.remove(0);
+                }
+                (LoopMode::Track, _) => (),
+                (LoopMode::Queue, _) => {
+                    let track = self.queue.remove(0);
+
+                    self.queue.push(track);
+                }
+            }
+        }
+
+        self.started(0);
+
+        match self.queue.first() {
+            Some(track) => self.send_play(track),
+            None => self.playing = false,
+        }
+    }
+
+    /// Removes an upcoming track, index 0 is the current track and can't be removed.
+    pub fn remove(&mut self, index: usize) -> Option<Track> {
+        if index == 0 || index >= self.queue.len() {
+            return None;
+        }
+
+        Some(self.queue.remove(index))
+    }
+
+    pub fn move_track(&mut self, from: usize, to: usize) -> Option<&Track> {
+        let len = self.queue.len();
+
+        if from == 0 || to == 0 || from >= len || to >= len {
+            return None;
+        }
+
+        let track = self.queue.remove(from);
+
+        self.queue.insert(to, track);
+
+        self.queue.get(to)
+    }
+
+    /// Drops every upcoming track, returns how many were removed.
+    pub fn clear(&mut self) -> usize {
+        let removed = self.queue.len().saturating_sub(1);
+
+        self.queue.truncate(1);
+
+        removed
     }
 
-    pub fn skip(&self) -> Option<&Track> {
+    pub fn shuffle(&mut self) {
+        if self.queue.len() > 2 {
+            self.queue[1..].shuffle(&mut rand::thread_rng());
+        }
+    }
+
+    /// Jumps to an upcoming track, dropping everything before it.
+    pub fn skip_to(&mut self, index: usize) -> Option<&Track> {
+        if index == 0 || index >= self.queue.len() {
+            return None;
+        }
+
+        let skipped: Vec<Track> = self.queue.drain(..index).collect();
+
+        if self.loop_mode == LoopMode::Queue {
+            self.queue.extend(skipped);
+        }
+
+        self.started(0);
+
+        let track = self.queue.first()?;
+
+        self.send_play(track);
+
+        Some(track)
+    }
+
+    /// Skips the current track, even when it is looping. The next track replaces
+    /// it directly, so the resulting `REPLACED`/`STOPPED` track end does not move
+    /// the queue again.
+    pub fn skip(&mut self) -> Option<Track> {
         if self.queue.is_empty() {
             return None;
         }
 
-        let track = self.queue.first();
+        let track = self.queue.remove(0);
 
-        self.send(
-            json!({
-                "op": "stop",
-                "guildId": self.guild_id
-            })
-            .to_string(),
+        if self.loop_mode == LoopMode::Queue {
+            self.queue.push(track.clone());
+        }
+
+        self.started(0);
+
+        match self.queue.first() {
+            Some(next) => self.send_play(next),
+            None => {
+                self.playing = false;
+
+                self.stop();
+            }
+        }
+
+        Some(track)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use serde_json::Value;
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use super::event_loop::LavalinkPayload;
+    use super::events::{CpuStats, NodeStats, PlayerState, TrackEndReason};
+    use super::{
+        normalize_command, LavalinkBuilderOptions, LavalinkClient, LavalinkVersion, LoopMode,
+        Player, Track, TrackInfo,
+    };
+    use crate::commands::Commands;
+    use crate::locale::Locales;
+
+    fn options(name: &str, version: LavalinkVersion) -> LavalinkBuilderOptions {
+        LavalinkBuilderOptions {
+            name: String::from(name),
+            host: String::from("localhost"),
+            port: 2333,
+            password: String::new(),
+            version,
+        }
+    }
+
+    fn player(tracks: &[&str]) -> (Player, UnboundedReceiver<LavalinkPayload>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let mut player = Player::new(
+            String::from("guild"),
+            String::from("channel"),
+            100,
+            Arc::new(tx),
+            Rc::new(options("node", LavalinkVersion::V4)),
+        );
+
+        player.queue = tracks.iter().map(|name| track(name)).collect();
+        player.playing = !tracks.is_empty();
+
+        (player, rx)
+    }
+
+    fn track(name: &str) -> Track {
+        Track {
+            track: String::from(name),
+            info: TrackInfo {
+                identifier: String::from(name),
+                author: String::new(),
+                length: 1000,
+                position: 0,
+                title: String::from(name),
+                uri: None,
+                is_stream: false,
+                is_seekable: true,
+            },
+            requester: None,
+        }
+    }
+
+    fn queue(player: &Player) -> Vec<&str> {
+        player
+            .queue
+            .iter()
+            .map(|track| track.track.as_str())
+            .collect()
+    }
+
+    /// Every payload sent to the node so far, `Some(track)` for a play and `None` for a stop.
+    fn played(rx: &mut UnboundedReceiver<LavalinkPayload>) -> Vec<Option<String>> {
+        let mut played = vec![];
+
+        while let Ok(payload) = rx.try_recv() {
+            if let LavalinkPayload::Update { body, .. } = payload {
+                played.push(body["track"]["encoded"].as_str().map(String::from));
+            }
+        }
+
+        played
+    }
+
+    fn registered() -> Vec<Value> {
+        serde_json::from_str(include_str!(
+            "../../tests/fixtures/registered_commands.json"
+        ))
+        .unwrap()
+    }
+
+    /// The catalogs the command definitions are translated from.
+    fn locales() -> Locales {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(Locales::load("locales", String::from("cs")))
+            .unwrap()
+    }
+
+    fn local(name: &str) -> Value {
+        let mut command = Commands::new()
+            .definitions()
+            .into_iter()
+            .find(|command| command.name == name)
+            .unwrap();
+
+        command.localize(&locales());
+
+        normalize_command(&serde_json::to_value(command).unwrap())
+    }
+
+    #[test]
+    fn registered_command_matches_local_definition() {
+        for remote in registered() {
+            let name = remote["name"].as_str().unwrap();
+
+            assert_eq!(normalize_command(&remote), local(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn missing_localizations_are_a_change() {
+        for mut remote in registered() {
+            let name = String::from(remote["name"].as_str().unwrap());
+
+            remote.as_object_mut().unwrap().remove("name_localizations");
+
+            assert_ne!(normalize_command(&remote), local(&name), "{name}");
+        }
+    }
+
+    #[test]
+    fn remove_keeps_the_current_track() {
+        let (mut player, _rx) = player(&["a", "b", "c"]);
+
+        assert!(player.remove(0).is_none());
+        assert!(player.remove(3).is_none());
+        assert_eq!(player.remove(1).unwrap().track, "b");
+        assert_eq!(queue(&player), ["a", "c"]);
+    }
+
+    #[test]
+    fn move_track_within_upcoming_tracks() {
+        let (mut player, _rx) = player(&["a", "b", "c", "d"]);
+
+        assert!(player.move_track(0, 2).is_none());
+        assert!(player.move_track(2, 0).is_none());
+        assert!(player.move_track(1, 4).is_none());
+        assert!(player.move_track(4, 1).is_none());
+        assert_eq!(queue(&player), ["a", "b", "c", "d"]);
+
+        assert_eq!(player.move_track(3, 1).unwrap().track, "d");
+        assert_eq!(queue(&player), ["a", "d", "b", "c"]);
+
+        assert_eq!(player.move_track(1, 3).unwrap().track, "d");
+        assert_eq!(queue(&player), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn clear_keeps_the_current_track() {
+        let (mut player, _rx) = player(&["a", "b", "c"]);
+
+        assert_eq!(player.clear(), 2);
+        assert_eq!(queue(&player), ["a"]);
+
+        let (mut player, _rx) = self::player(&[]);
+
+        assert_eq!(player.clear(), 0);
+        assert!(player.queue.is_empty());
+    }
+
+    #[test]
+    fn shuffle_keeps_the_current_track() {
+        let (mut player, _rx) = player(&["a", "b", "c", "d", "e"]);
+
+        player.shuffle();
+
+        let mut shuffled = queue(&player);
+
+        assert_eq!(shuffled[0], "a");
+
+        shuffled.sort_unstable();
+
+        assert_eq!(shuffled, ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn skip_to_drops_the_skipped_tracks() {
+        let (mut player, mut rx) = player(&["a", "b", "c", "d"]);
+
+        assert!(player.skip_to(0).is_none());
+        assert!(player.skip_to(4).is_none());
+        assert!(played(&mut rx).is_empty());
+
+        assert_eq!(player.skip_to(2).unwrap().track, "c");
+        assert_eq!(queue(&player), ["c", "d"]);
+        assert_eq!(played(&mut rx), [Some(String::from("c"))]);
+    }
+
+    #[test]
+    fn skip_to_requeues_the_skipped_tracks_when_looping_the_queue() {
+        let (mut player, mut rx) = player(&["a", "b", "c", "d"]);
+
+        player.loop_mode = LoopMode::Queue;
+
+        assert_eq!(player.skip_to(2).unwrap().track, "c");
+        assert_eq!(queue(&player), ["c", "d", "a", "b"]);
+        assert_eq!(played(&mut rx), [Some(String::from("c"))]);
+    }
+
+    #[test]
+    fn skip_ignores_track_loop() {
+        let (mut player, mut rx) = player(&["a", "b"]);
+
+        player.loop_mode = LoopMode::Track;
+
+        assert_eq!(player.skip().unwrap().track, "a");
+        assert_eq!(queue(&player), ["b"]);
+        assert!(player.playing);
+        assert_eq!(played(&mut rx), [Some(String::from("b"))]);
+    }
+
+    #[test]
+    fn skip_last_track_stops_the_player() {
+        let (mut player, mut rx) = player(&["a"]);
+
+        assert_eq!(player.skip().unwrap().track, "a");
+        assert!(player.queue.is_empty());
+        assert!(!player.playing);
+        assert_eq!(played(&mut rx), [None]);
+
+        assert!(player.skip().is_none());
+    }
+
+    #[test]
+    fn skip_last_track_when_looping_the_queue_plays_it_again() {
+        let (mut player, mut rx) = player(&["a"]);
+
+        player.loop_mode = LoopMode::Queue;
+
+        assert_eq!(player.skip().unwrap().track, "a");
+        assert_eq!(queue(&player), ["a"]);
+        assert!(player.playing);
+        assert_eq!(played(&mut rx), [Some(String::from("a"))]);
+    }
+
+    #[test]
+    fn advance_follows_the_loop_mode() {
+        let (mut player, mut rx) = player(&["a", "b"]);
+
+        player.loop_mode = LoopMode::Track;
+        player.advance(TrackEndReason::Finished);
+
+        assert_eq!(queue(&player), ["a", "b"]);
+
+        player.loop_mode = LoopMode::Queue;
+        player.advance(TrackEndReason::Finished);
+
+        assert_eq!(queue(&player), ["b", "a"]);
+
+        player.loop_mode = LoopMode::Off;
+        player.advance(TrackEndReason::Finished);
+
+        assert_eq!(queue(&player), ["a"]);
+        assert_eq!(
+            played(&mut rx),
+            [
+                Some(String::from("a")),
+                Some(String::from("b")),
+                Some(String::from("a"))
+            ]
+        );
+
+        player.advance(TrackEndReason::Finished);
+
+        assert!(player.queue.is_empty());
+        assert!(!player.playing);
+        assert!(played(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn advance_never_loops_a_failed_track() {
+        let (mut player, _rx) = player(&["a", "b"]);
+
+        player.loop_mode = LoopMode::Track;
+        player.advance(TrackEndReason::LoadFailed);
+
+        assert_eq!(queue(&player), ["b"]);
+    }
+
+    #[test]
+    fn link_falls_back_to_the_title() {
+        let mut track = track("a");
+
+        assert_eq!(track.info.link(), "a");
+
+        track.info.uri = Some(String::from("https://example.com/a"));
+
+        assert_eq!(track.info.link(), "[a](https://example.com/a)");
+    }
+
+    /// Pretends the last position was recorded `ms` milliseconds ago.
+    fn rewind(player: &mut Player, ms: u64) {
+        player.updated_at = Instant::now() - Duration::from_millis(ms);
+    }
+
+    #[test]
+    fn position_interpolates_from_the_play_until_the_first_update() {
+        let (mut player, _rx) = player(&[]);
+
+        player.play(track("a"));
+        rewind(&mut player, 300);
+
+        assert!((300..1000).contains(&player.position()));
+
+        player.update_state(PlayerState {
+            position: 100,
+            connected: false,
+            ..PlayerState::default()
+        });
+        rewind(&mut player, 300);
+
+        assert_eq!(player.position(), 100);
+
+        player.update_state(PlayerState {
+            position: 100,
+            connected: true,
+            ..PlayerState::default()
+        });
+        rewind(&mut player, 300);
+
+        assert!((400..1000).contains(&player.position()));
+    }
+
+    #[test]
+    fn position_stops_while_paused() {
+        let (mut player, _rx) = player(&["a"]);
+
+        player.update_state(PlayerState {
+            position: 100,
+            connected: true,
+            ..PlayerState::default()
+        });
+        player.paused = true;
+        rewind(&mut player, 300);
+
+        assert_eq!(player.position(), 100);
+    }
+
+    #[test]
+    fn position_is_capped_at_the_track_length() {
+        let (mut player, _rx) = player(&["a"]);
+
+        player.update_state(PlayerState {
+            position: 900,
+            connected: true,
+            ..PlayerState::default()
+        });
+        rewind(&mut player, 300);
+
+        assert_eq!(player.position(), 1000);
+
+        player.queue[0].info.is_stream = true;
+
+        assert!(player.position() >= 1200);
+    }
+
+    /// Every node is connected, as far as picking a node is concerned.
+    fn pool(nodes: &[(&str, LavalinkVersion)]) -> LavalinkClient {
+        let mut client = LavalinkClient::new(
+            nodes
+                .iter()
+                .map(|(name, version)| options(name, *version))
+                .collect(),
+        );
+
+        for node in &mut client.nodes {
+            node.available = true;
+            node.socket.sender = Some(Arc::new(mpsc::unbounded_channel().0));
+        }
+
+        client
+    }
+
+    fn stats(playing_players: u32, system_load: f64) -> NodeStats {
+        NodeStats {
+            players: playing_players,
+            playing_players,
+            uptime: 0,
+            cpu: CpuStats {
+                cores: 4,
+                system_load,
+                lavalink_load: system_load,
+            },
+            frame_stats: None,
+        }
+    }
+
+    fn add_player(client: &mut LavalinkClient, guild_id: &str, node: &str) {
+        let node = client.nodes.iter().find(|n| n.name() == node).unwrap();
+
+        let player = Player::new(
+            String::from(guild_id),
+            String::from("channel"),
+            100,
+            Arc::clone(node.socket.sender.as_ref().unwrap()),
+            Rc::clone(&node.socket.options),
         );
 
-        track
+        client.players.push(player);
+    }
+
+    #[test]
+    fn penalty_without_stats_is_the_player_count() {
+        let client = pool(&[("a", LavalinkVersion::V4)]);
+
+        assert_eq!(client.nodes[0].penalty(0), 0.0);
+        assert_eq!(client.nodes[0].penalty(3), 3.0);
+    }
+
+    #[test]
+    fn penalty_counts_reported_players_once() {
+        let mut client = pool(&[("a", LavalinkVersion::V4)]);
+
+        let reported = stats(2, 0.0);
+        let base = reported.penalty();
+
+        client.nodes[0].stats = Some(reported);
+
+        assert_eq!(base, 2.0);
+        assert_eq!(client.nodes[0].penalty(2), base);
+        assert_eq!(client.nodes[0].penalty(1), base);
+        assert_eq!(client.nodes[0].penalty(5), base + 3.0);
+    }
+
+    #[test]
+    fn best_node_is_the_least_loaded_available_one() {
+        let mut client = pool(&[
+            ("a", LavalinkVersion::V4),
+            ("b", LavalinkVersion::V4),
+            ("c", LavalinkVersion::V4),
+        ]);
+
+        client.nodes[0].stats = Some(stats(0, 0.9));
+        client.nodes[1].stats = Some(stats(0, 0.0));
+
+        add_player(&mut client, "1", "c");
+        add_player(&mut client, "2", "c");
+
+        assert_eq!(client.best_node().unwrap().name(), "b");
+
+        client.nodes[1].available = false;
+
+        assert_eq!(client.best_node().unwrap().name(), "c");
+
+        client.nodes[2].socket.sender = None;
+
+        assert_eq!(client.best_node().unwrap().name(), "a");
+
+        client.nodes[0].available = false;
+
+        assert!(client.best_node().is_none());
+    }
+
+    #[test]
+    fn failover_only_picks_nodes_of_the_same_version() {
+        let mut client = pool(&[
+            ("a", LavalinkVersion::V3),
+            ("b", LavalinkVersion::V4),
+            ("c", LavalinkVersion::V3),
+        ]);
+
+        client.nodes[1].stats = Some(stats(0, 0.0));
+        client.nodes[2].stats = Some(stats(10, 0.5));
+
+        assert_eq!(
+            client.failover_node(LavalinkVersion::V3).unwrap().name(),
+            "a"
+        );
+        assert_eq!(
+            client.failover_node(LavalinkVersion::V4).unwrap().name(),
+            "b"
+        );
+
+        client.nodes[0].available = false;
+
+        assert_eq!(
+            client.failover_node(LavalinkVersion::V3).unwrap().name(),
+            "c"
+        );
+
+        client.nodes[2].available = false;
+
+        assert!(client.failover_node(LavalinkVersion::V3).is_none());
+    }
+
+    #[test]
+    fn failover_moves_players_to_a_node_of_the_same_version() {
+        let mut client = pool(&[
+            ("a", LavalinkVersion::V3),
+            ("b", LavalinkVersion::V4),
+            ("c", LavalinkVersion::V3),
+        ]);
+
+        add_player(&mut client, "1", "a");
+        add_player(&mut client, "2", "b");
+
+        client.nodes[0].available = false;
+        client.failover("a");
+
+        assert_eq!(client.get_player("1").unwrap().node(), "c");
+        assert_eq!(client.get_player("2").unwrap().node(), "b");
+
+        client.nodes[2].available = false;
+        client.failover("c");
+
+        // the v4 node can't play v3 tracks, so the player waits for a v3 node
+        assert_eq!(client.get_player("1").unwrap().node(), "c");
     }
 }
@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::{Method, Response, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+const API: &str = "https://discord.com/api/v10";
+/// How many times a request is sent again after a 429 before giving up.
+const MAX_RETRIES: u32 = 3;
+/// Longest a request waits on a rate limit, the time an interaction has to be
+/// answered. A longer wait fails right away instead of holding up the caller.
+const MAX_WAIT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Default)]
+struct Bucket {
+    remaining: Option<u64>,
+    reset_at: Option<Instant>,
+}
+
+/// The one http client for the Discord API. Keeps track of the rate limit
+/// buckets Discord reports, requests to an exhausted bucket wait until it resets
+/// and a 429 is retried after `retry_after`, as long as that's within `MAX_WAIT`.
+#[derive(Debug, Default)]
+pub struct Rest {
+    client: reqwest::Client,
+    token: Option<String>,
+    /// The bucket of every route whose `X-RateLimit-Bucket` is known.
+    routes: Mutex<HashMap<String, String>>,
+    buckets: Mutex<HashMap<String, Arc<Mutex<Bucket>>>>,
+    global_reset_at: Mutex<Option<Instant>>,
+}
+
+impl Rest {
+    pub fn new(token: String) -> Self {
+        Self {
+            token: Some(token),
+            ..Self::default()
+        }
+    }
+
+    pub async fn get(&self, path: &str) -> Result<Response, String> {
+        self.request::<()>(Method::GET, path, None).await
+    }
+
+    pub async fn post<T: Serialize>(&self, path: &str, body: &T) -> Result<Response, String> {
+        self.request(Method::POST, path, Some(body)).await
+    }
+
+    pub async fn put<T: Serialize>(&self, path: &str, body: &T) -> Result<Response, String> {
+        self.request(Method::PUT, path, Some(body)).await
+    }
+
+    pub async fn patch<T: Serialize>(&self, path: &str, body: &T) -> Result<Response, String> {
+        self.request(Method::PATCH, path, Some(body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> Result<Response, String> {
+        self.request::<()>(Method::DELETE, path, None).await
+    }
+
+    /// Sends a request to `path` (relative to the api root), returns the response
+    /// only when it succeeded.
+    pub async fn request<T: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&T>,
+    ) -> Result<Response, String> {
+        let res = self.send(method.clone(), path, body).await?;
+
+        if res.status().is_success() {
+            return Ok(res);
+        }
+
+        let status = res.status();
+
+        let message = res.text().await.unwrap_or_default();
+
+        Err(format!("Err: {status}, {method} {path}: {message}"))
+    }
+
+    /// Like `request`, but any response that isn't a 429 is returned, for callers
+    /// that handle some of the errors themselves.
+    pub async fn send<T: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&T>,
+    ) -> Result<Response, String> {
+        let body = match body {
+            Some(body) => Some(
+                serde_json::to_value(body)
+                    .map_err(|err| format!("Error serializing request body:\n\n{err}"))?,
+            ),
+            None => None,
+        };
+
+        let route = route(&method, path);
+
+        let shared = self.bucket(&route).await;
+
+        // held until the response is in, so requests to the same bucket queue up here
+        let mut bucket = shared.lock().await;
+
+        for _ in 0..=MAX_RETRIES {
+            if let Some(reset_at) = *self.global_reset_at.lock().await {
+                wait_until(reset_at, &method, path).await?;
+            }
+
+            if let (Some(0), Some(reset_at)) = (bucket.remaining, bucket.reset_at) {
+                wait_until(reset_at, &method, path).await?;
+            }
+
+            let mut req = self.client.request(method.clone(), format!("{API}{path}"));
+
+            if let Some(token) = &self.token {
+                req = req.header("Authorization", format!("Bot {token}"));
+            }
+
+            if let Some(body) = &body {
+                req = req.json(body);
+            }
+
+            let res = req
+                .send()
+                .await
+                .map_err(|err| format!("Error sending request to {path}:\n\n{err}"))?;
+
+            update_bucket(&mut bucket, &res);
+
+            self.learn_bucket(&route, path, &shared, &res).await;
+
+            if res.status() == StatusCode::TOO_MANY_REQUESTS {
+                let limit: Value = res.json().await.unwrap_or_default();
+
+                let retry_after =
+                    Duration::from_secs_f64(limit["retry_after"].as_f64().unwrap_or(1.0));
+
+                println!("Rate limited on {method} {path}, retrying in {retry_after:?}");
+
+                if limit["global"].as_bool().unwrap_or(false) {
+                    *self.global_reset_at.lock().await = Some(Instant::now() + retry_after);
+                } else {
+                    bucket.remaining = Some(0);
+                    bucket.reset_at = Some(Instant::now() + retry_after);
+                }
+
+                continue;
+            }
+
+            return Ok(res);
+        }
+
+        Err(format!("Still rate limited on {method} {path}"))
+    }
+
+    /// The bucket of `route`, until Discord names its bucket the route is its own.
+    async fn bucket(&self, route: &str) -> Arc<Mutex<Bucket>> {
+        let key = self.routes.lock().await.get(route).cloned();
+
+        let key = key.unwrap_or_else(|| String::from(route));
+
+        let mut buckets = self.buckets.lock().await;
+
+        // interaction tokens make a new bucket for every interaction, forget the
+        // ones nobody waits on and that are not limited anymore
+        if !buckets.contains_key(&key) {
+            let now = Instant::now();
+
+            buckets.retain(|_, bucket| {
+                Arc::strong_count(bucket) > 1
+                    || bucket
+                        .try_lock()
+                        .map(|b| b.reset_at.is_some_and(|reset_at| reset_at > now))
+                        .unwrap_or(true)
+            });
+
+            self.routes
+                .lock()
+                .await
+                .retain(|_, bucket| buckets.contains_key(bucket));
+        }
+
+        Arc::clone(buckets.entry(key).or_default())
+    }
+
+    /// Remembers the bucket Discord put `route` in. Routes sharing a bucket hash
+    /// share its limit when their major parameters are the same too.
+    async fn learn_bucket(
+        &self,
+        route: &str,
+        path: &str,
+        shared: &Arc<Mutex<Bucket>>,
+        res: &Response,
+    ) {
+        let Some(hash) = res
+            .headers()
+            .get("x-ratelimit-bucket")
+            .and_then(|value| value.to_str().ok())
+        else {
+            return;
+        };
+
+        let key = format!("{hash}:{}", major_parameters(path));
+
+        let mut routes = self.routes.lock().await;
+
+        if routes.get(route) == Some(&key) {
+            return;
+        }
+
+        routes.insert(String::from(route), key.clone());
+
+        drop(routes);
+
+        self.buckets
+            .lock()
+            .await
+            .entry(key)
+            .or_insert_with(|| Arc::clone(shared));
+    }
+}
+
+/// Waits for a rate limit to reset, or fails when that would take longer than `MAX_WAIT`.
+async fn wait_until(reset_at: Instant, method: &Method, path: &str) -> Result<(), String> {
+    let wait = reset_at.saturating_duration_since(Instant::now());
+
+    if wait > MAX_WAIT {
+        return Err(format!(
+            "Rate limited on {method} {path} for another {wait:?}, not waiting"
+        ));
+    }
+
+    tokio::time::sleep_until(reset_at).await;
+
+    Ok(())
+}
+
+fn update_bucket(bucket: &mut Bucket, res: &Response) {
+    let header = |name: &str| {
+        res.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+
+    if let Some(remaining) = header("x-ratelimit-remaining") {
+        bucket.remaining = Some(remaining as u64);
+    }
+
+    if let Some(reset_after) = header("x-ratelimit-reset-after") {
+        bucket.reset_at = Some(Instant::now() + Duration::from_secs_f64(reset_after));
+    }
+}
+
+/// The client of a value that came from serde, which can't fill it in, so it
+/// is set afterwards and an error until then.
+pub fn required(rest: &Option<Arc<Rest>>) -> Result<&Rest, String> {
+    rest.as_deref()
+        .ok_or_else(|| String::from("No rest client set, can't call the Discord API"))
+}
+
+/// Discord limits per route, where ids count as the same route except for the
+/// major parameters (channel, guild, webhook and its token).
+fn route(method: &Method, path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+
+    let mut route = method.to_string();
+    let mut previous = "";
+
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        let major = matches!(previous, "channels" | "guilds" | "webhooks");
+
+        let is_id = segment.chars().all(|c| c.is_ascii_digit());
+
+        route.push('/');
+
+        if is_id && !major {
+            route.push_str(":id");
+        } else {
+            route.push_str(segment);
+        }
+
+        previous = segment;
+    }
+
+    route
+}
+
+/// The major parameters of `path`: the channel, guild or webhook with its token.
+fn major_parameters(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let mut major: Vec<&str> = vec![];
+
+    for (i, segment) in segments.iter().enumerate() {
+        match *segment {
+            "channels" | "guilds" => major.extend(segments.get(i + 1)),
+            "webhooks" => major.extend(segments.iter().skip(i + 1).take(2)),
+            _ => (),
+        }
+    }
+
+    major.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Method;
+
+    use super::{major_parameters, route};
+
+    #[test]
+    fn route_keeps_only_major_parameters() {
+        assert_eq!(
+            route(&Method::PATCH, "/channels/1/messages/2"),
+            "PATCH/channels/1/messages/:id"
+        );
+        assert_eq!(
+            route(&Method::GET, "/guilds/3/members/4"),
+            "GET/guilds/3/members/:id"
+        );
+        assert_eq!(
+            route(&Method::POST, "/webhooks/5/token/messages/6"),
+            "POST/webhooks/5/token/messages/:id"
+        );
+        assert_eq!(
+            route(&Method::GET, "/applications/7/commands/8"),
+            "GET/applications/:id/commands/:id"
+        );
+    }
+
+    #[test]
+    fn route_ignores_the_query() {
+        assert_eq!(
+            route(&Method::POST, "/webhooks/5/token?wait=true"),
+            route(&Method::POST, "/webhooks/5/token")
+        );
+    }
+
+    #[test]
+    fn routes_differ_by_major_parameter() {
+        assert_ne!(
+            route(&Method::POST, "/channels/1/messages"),
+            route(&Method::POST, "/channels/2/messages")
+        );
+        assert_eq!(
+            route(&Method::DELETE, "/channels/1/messages/2"),
+            route(&Method::DELETE, "/channels/1/messages/3")
+        );
+    }
+
+    #[test]
+    fn major_parameters_of_a_path() {
+        assert_eq!(major_parameters("/channels/1/messages/2"), "1");
+        assert_eq!(major_parameters("/guilds/3/members/4"), "3");
+        assert_eq!(
+            major_parameters("/webhooks/5/token/messages/@original?wait=true"),
+            "5/token"
+        );
+        assert_eq!(major_parameters("/applications/7/commands"), "");
+    }
+}
@@ -1,24 +1,176 @@
+use std::collections::BTreeMap;
+
 use serde::{self, Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct NameLocalization {
-    pub cs: String,
+use crate::locale::Locales;
+
+/// Translations keyed by Discord locale, e.g. `cs` or `en-GB`.
+pub type Localizations = BTreeMap<String, String>;
+
+/// Locales Discord accepts in localizations, other catalogs only translate messages.
+const DISCORD_LOCALES: &[&str] = &[
+    "id", "da", "de", "en-GB", "en-US", "es-ES", "es-419", "fr", "hr", "it", "lt", "hu", "nl",
+    "no", "pl", "pt-BR", "ro", "fi", "sv-SE", "vi", "tr", "cs", "el", "bg", "ru", "uk", "hi", "th",
+    "zh-CN", "ja", "zh-TW", "ko",
+];
+
+/// Discord's limits for command definitions, checked before registering.
+const MAX_NAME_LENGTH: usize = 32;
+const MAX_DESCRIPTION_LENGTH: usize = 100;
+const MAX_OPTIONS: usize = 25;
+const MAX_CHOICES: usize = 25;
+const MAX_CHOICE_NAME_LENGTH: usize = 100;
+const MAX_CHOICE_VALUE_LENGTH: usize = 100;
+const MAX_STRING_LENGTH: u16 = 6000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum OptionType {
+    SubCommand,
+    SubCommandGroup,
+    String,
+    Integer,
+    Boolean,
+    User,
+    Channel,
+    Role,
+    Mentionable,
+    Number,
+    Attachment,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct DescLocalization {
-    pub cs: String,
+impl From<OptionType> for u8 {
+    fn from(option_type: OptionType) -> Self {
+        match option_type {
+            OptionType::SubCommand => 1,
+            OptionType::SubCommandGroup => 2,
+            OptionType::String => 3,
+            OptionType::Integer => 4,
+            OptionType::Boolean => 5,
+            OptionType::User => 6,
+            OptionType::Channel => 7,
+            OptionType::Role => 8,
+            OptionType::Mentionable => 9,
+            OptionType::Number => 10,
+            OptionType::Attachment => 11,
+        }
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl TryFrom<u8> for OptionType {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(OptionType::SubCommand),
+            2 => Ok(OptionType::SubCommandGroup),
+            3 => Ok(OptionType::String),
+            4 => Ok(OptionType::Integer),
+            5 => Ok(OptionType::Boolean),
+            6 => Ok(OptionType::User),
+            7 => Ok(OptionType::Channel),
+            8 => Ok(OptionType::Role),
+            9 => Ok(OptionType::Mentionable),
+            10 => Ok(OptionType::Number),
+            11 => Ok(OptionType::Attachment),
+            _ => Err(format!("unknown option type {value}")),
+        }
+    }
+}
+
+/// Value of a choice, its type has to match the type of the option.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ChoiceValue {
+    Integer(i64),
+    Number(f64),
+    String(String),
+}
+
+/// Bound of an integer or number option.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum OptionBound {
+    Integer(i64),
+    Number(f64),
+}
+
+impl OptionBound {
+    fn as_f64(self) -> f64 {
+        match self {
+            OptionBound::Integer(value) => value as f64,
+            OptionBound::Number(value) => value,
+        }
+    }
+}
+
+fn validate_name(name: &str, localized: Option<&Localizations>) -> Result<(), String> {
+    for name in std::iter::once(name).chain(
+        localized
+            .into_iter()
+            .flat_map(|l| l.values().map(String::as_str)),
+    ) {
+        let length = name.chars().count();
+
+        if length == 0 || length > MAX_NAME_LENGTH {
+            return Err(format!(
+                "name {name} has to be 1-{MAX_NAME_LENGTH} characters long"
+            ));
+        }
+
+        if name.to_lowercase() != name {
+            return Err(format!("name {name} has to be lowercase"));
+        }
+
+        if !name.chars().all(is_name_char) {
+            return Err(format!(
+                "name {name} can only contain letters, numbers, - and _"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Discord's `^[-_\p{L}\p{N}\p{sc=Deva}\p{sc=Thai}]{1,32}$`, Devanagari and
+/// Thai are allowed whole because their vowel signs aren't letters.
+fn is_name_char(c: char) -> bool {
+    matches!(c, '-' | '_' | '\u{0900}'..='\u{097F}' | '\u{0E00}'..='\u{0E7F}')
+        || c.is_alphanumeric()
+}
+
+/// Adds the translations of `key` from every catalog of a Discord locale.
+fn localize(localizations: &mut Option<Localizations>, locales: &Locales, key: &str) {
+    for (locale, text) in locales.translations(key) {
+        if DISCORD_LOCALES.contains(&locale) {
+            localizations
+                .get_or_insert_with(Localizations::new)
+                .insert(String::from(locale), String::from(text));
+        }
+    }
+}
+
+fn validate_description(name: &str, description: &str) -> Result<(), String> {
+    let length = description.chars().count();
+
+    if length == 0 || length > MAX_DESCRIPTION_LENGTH {
+        return Err(format!(
+            "description of {name} has to be 1-{MAX_DESCRIPTION_LENGTH} characters long"
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ApplicationCommand {
     #[serde(rename = "type")]
     pub command_type: u32,
     pub name: String,
     pub description: String,
     pub options: Vec<ApplicationCommandOption>,
-    pub name_localizations: Option<NameLocalization>,
-    pub description_localizations: Option<DescLocalization>,
+    pub name_localizations: Option<Localizations>,
+    pub description_localizations: Option<Localizations>,
 }
 
 impl ApplicationCommand {
@@ -37,44 +189,135 @@ impl ApplicationCommand {
         self.options.push(option);
     }
 
-    pub fn set_name_loc(mut self, name: &str) -> Self {
-        self.name_localizations = Some(NameLocalization {
-            cs: String::from(name),
-        });
+    pub fn set_name_loc(mut self, locale: &str, name: &str) -> Self {
+        self.name_localizations
+            .get_or_insert_with(Localizations::new)
+            .insert(String::from(locale), String::from(name));
 
         self
     }
 
-    pub fn set_desc_loc(mut self, desc: &str) -> Self {
-        self.description_localizations = Some(DescLocalization {
-            cs: String::from(desc),
-        });
+    pub fn set_desc_loc(mut self, locale: &str, desc: &str) -> Self {
+        self.description_localizations
+            .get_or_insert_with(Localizations::new)
+            .insert(String::from(locale), String::from(desc));
 
         self
     }
+
+    /// Translates the command from the `commands.<name>` keys of the catalogs,
+    /// with `name` and `description` keys and its options under `options.<name>`.
+    pub fn localize(&mut self, locales: &Locales) {
+        let key = format!("commands.{}", self.name);
+
+        localize(
+            &mut self.name_localizations,
+            locales,
+            &format!("{key}.name"),
+        );
+        localize(
+            &mut self.description_localizations,
+            locales,
+            &format!("{key}.description"),
+        );
+
+        for option in &mut self.options {
+            option.localize(locales, &key);
+        }
+    }
+
+    /// Checks the command against Discord's limits, so a bad definition fails
+    /// with a readable error instead of a rejected request.
+    pub fn validate(&self) -> Result<(), String> {
+        let name = &self.name;
+
+        validate_name(name, self.name_localizations.as_ref())?;
+        validate_description(name, &self.description)?;
+
+        if self.options.len() > MAX_OPTIONS {
+            return Err(format!(
+                "command {name} has more than {MAX_OPTIONS} options"
+            ));
+        }
+
+        for option in &self.options {
+            option
+                .validate()
+                .map_err(|err| format!("{err}, in command {name}"))?;
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ApplicationCommandOption {
     pub name: String,
     pub description: String,
-    pub name_localizations: Option<NameLocalization>,
-    pub description_localizations: Option<DescLocalization>,
+    pub name_localizations: Option<Localizations>,
+    pub description_localizations: Option<Localizations>,
     #[serde(rename = "type")]
-    pub option_type: u32,
+    pub option_type: OptionType,
     pub required: bool,
     pub choices: Vec<ApplicationCommandOptionChoice>,
     pub options: Vec<ApplicationCommandOption>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_value: Option<OptionBound>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_value: Option<OptionBound>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channel_types: Vec<u8>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub autocomplete: bool,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ApplicationCommandOptionChoice {
     pub name: String,
-    pub value: String,
+    pub value: ChoiceValue,
+    pub name_localizations: Option<Localizations>,
+}
+
+impl ApplicationCommandOptionChoice {
+    pub fn new(name: String, value: String) -> Self {
+        Self {
+            name,
+            value: ChoiceValue::String(value),
+            name_localizations: None,
+        }
+    }
+
+    pub fn integer(name: String, value: i64) -> Self {
+        Self {
+            name,
+            value: ChoiceValue::Integer(value),
+            name_localizations: None,
+        }
+    }
+
+    pub fn number(name: String, value: f64) -> Self {
+        Self {
+            name,
+            value: ChoiceValue::Number(value),
+            name_localizations: None,
+        }
+    }
+
+    pub fn set_name_loc(mut self, locale: &str, name: &str) -> Self {
+        self.name_localizations
+            .get_or_insert_with(Localizations::new)
+            .insert(String::from(locale), String::from(name));
+
+        self
+    }
 }
 
 impl ApplicationCommandOption {
-    pub fn new(name: String, description: String, option_type: u32, required: bool) -> Self {
+    pub fn new(name: String, description: String, option_type: OptionType, required: bool) -> Self {
         Self {
             name,
             name_localizations: None,
@@ -84,6 +327,12 @@ impl ApplicationCommandOption {
             required,
             choices: vec![],
             options: vec![],
+            min_value: None,
+            max_value: None,
+            min_length: None,
+            max_length: None,
+            channel_types: vec![],
+            autocomplete: false,
         }
     }
 
@@ -95,19 +344,428 @@ impl ApplicationCommandOption {
         self.options.push(option);
     }
 
-    pub fn set_name_loc(mut self, name: &str) -> Self {
-        self.name_localizations = Some(NameLocalization {
-            cs: String::from(name),
-        });
+    pub fn set_min_value(mut self, min_value: i64) -> Self {
+        self.min_value = Some(OptionBound::Integer(min_value));
+
+        self
+    }
+
+    pub fn set_max_value(mut self, max_value: i64) -> Self {
+        self.max_value = Some(OptionBound::Integer(max_value));
+
+        self
+    }
+
+    /// Bounds of a number option, integer options use `set_min_value`.
+    pub fn set_min_number(mut self, min_value: f64) -> Self {
+        self.min_value = Some(OptionBound::Number(min_value));
+
+        self
+    }
+
+    pub fn set_max_number(mut self, max_value: f64) -> Self {
+        self.max_value = Some(OptionBound::Number(max_value));
+
+        self
+    }
+
+    /// Length limits of a string option.
+    pub fn set_min_length(mut self, min_length: u16) -> Self {
+        self.min_length = Some(min_length);
+
+        self
+    }
+
+    pub fn set_max_length(mut self, max_length: u16) -> Self {
+        self.max_length = Some(max_length);
 
         self
     }
 
-    pub fn set_desc_loc(mut self, desc: &str) -> Self {
-        self.description_localizations = Some(DescLocalization {
-            cs: String::from(desc),
-        });
+    /// Only used by channel options, e.g. `2` for voice channels.
+    pub fn set_channel_types(mut self, channel_types: Vec<u8>) -> Self {
+        self.channel_types = channel_types;
 
         self
     }
+
+    /// Suggestions come from the bot while typing, can't be combined with choices.
+    pub fn set_autocomplete(mut self, autocomplete: bool) -> Self {
+        self.autocomplete = autocomplete;
+
+        self
+    }
+
+    pub fn set_name_loc(mut self, locale: &str, name: &str) -> Self {
+        self.name_localizations
+            .get_or_insert_with(Localizations::new)
+            .insert(String::from(locale), String::from(name));
+
+        self
+    }
+
+    pub fn set_desc_loc(mut self, locale: &str, desc: &str) -> Self {
+        self.description_localizations
+            .get_or_insert_with(Localizations::new)
+            .insert(String::from(locale), String::from(desc));
+
+        self
+    }
+
+    /// Same keys as a command, under `options.<name>` of its parent. Choices are
+    /// translated by `choices.<value>`.
+    fn localize(&mut self, locales: &Locales, parent: &str) {
+        let key = format!("{parent}.options.{}", self.name);
+
+        localize(
+            &mut self.name_localizations,
+            locales,
+            &format!("{key}.name"),
+        );
+        localize(
+            &mut self.description_localizations,
+            locales,
+            &format!("{key}.description"),
+        );
+
+        for choice in &mut self.choices {
+            let value = match &choice.value {
+                ChoiceValue::String(value) => value.clone(),
+                ChoiceValue::Integer(value) => value.to_string(),
+                ChoiceValue::Number(value) => value.to_string(),
+            };
+
+            localize(
+                &mut choice.name_localizations,
+                locales,
+                &format!("{key}.choices.{value}"),
+            );
+        }
+
+        for option in &mut self.options {
+            option.localize(locales, &key);
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let name = &self.name;
+
+        validate_name(name, self.name_localizations.as_ref())?;
+        validate_description(name, &self.description)?;
+
+        if self.choices.len() > MAX_CHOICES {
+            return Err(format!("option {name} has more than {MAX_CHOICES} choices"));
+        }
+
+        if self.autocomplete && !self.choices.is_empty() {
+            return Err(format!(
+                "option {name} can't have both choices and autocomplete"
+            ));
+        }
+
+        if self.autocomplete
+            && !matches!(
+                self.option_type,
+                OptionType::String | OptionType::Integer | OptionType::Number
+            )
+        {
+            return Err(format!(
+                "option {name} can't use autocomplete, only string, integer and number options can"
+            ));
+        }
+
+        self.validate_limits()?;
+
+        for choice in &self.choices {
+            let names = std::iter::once(&choice.name).chain(
+                choice
+                    .name_localizations
+                    .iter()
+                    .flat_map(|localized| localized.values()),
+            );
+
+            for choice_name in names {
+                let length = choice_name.chars().count();
+
+                if length == 0 || length > MAX_CHOICE_NAME_LENGTH {
+                    return Err(format!(
+                        "choice {choice_name} of option {name} has to be 1-{MAX_CHOICE_NAME_LENGTH} characters long"
+                    ));
+                }
+            }
+
+            if let ChoiceValue::String(value) = &choice.value {
+                if value.chars().count() > MAX_CHOICE_VALUE_LENGTH {
+                    return Err(format!(
+                        "value of choice {} of option {name} can't be longer than {MAX_CHOICE_VALUE_LENGTH} characters",
+                        choice.name
+                    ));
+                }
+            }
+
+            let matches = matches!(
+                (&choice.value, self.option_type),
+                (ChoiceValue::String(_), OptionType::String)
+                    | (ChoiceValue::Integer(_), OptionType::Integer)
+                    | (
+                        ChoiceValue::Integer(_) | ChoiceValue::Number(_),
+                        OptionType::Number
+                    )
+            );
+
+            if !matches {
+                return Err(format!(
+                    "choice {} doesn't match the type of option {name}",
+                    choice.name
+                ));
+            }
+        }
+
+        if self.options.len() > MAX_OPTIONS {
+            return Err(format!("option {name} has more than {MAX_OPTIONS} options"));
+        }
+
+        for option in &self.options {
+            option
+                .validate()
+                .map_err(|err| format!("{err}, in option {name}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Value bounds only make sense for integer and number options, length
+    /// limits for string options and channel types for channel options.
+    fn validate_limits(&self) -> Result<(), String> {
+        let name = &self.name;
+
+        if self.min_value.is_some() || self.max_value.is_some() {
+            if !matches!(self.option_type, OptionType::Integer | OptionType::Number) {
+                return Err(format!(
+                    "option {name} can't have min_value or max_value, only integer and number options can"
+                ));
+            }
+
+            let is_number =
+                |bound: &Option<OptionBound>| matches!(bound, Some(OptionBound::Number(_)));
+
+            if self.option_type == OptionType::Integer
+                && (is_number(&self.min_value) || is_number(&self.max_value))
+            {
+                return Err(format!(
+                    "option {name} is an integer option, its bounds have to be integers"
+                ));
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.min_value, self.max_value) {
+            if min.as_f64() > max.as_f64() {
+                return Err(format!(
+                    "min_value of option {name} is greater than its max_value"
+                ));
+            }
+        }
+
+        if (self.min_length.is_some() || self.max_length.is_some())
+            && self.option_type != OptionType::String
+        {
+            return Err(format!(
+                "option {name} can't have min_length or max_length, only string options can"
+            ));
+        }
+
+        if let Some(min_length) = self.min_length {
+            if min_length > MAX_STRING_LENGTH {
+                return Err(format!(
+                    "min_length of option {name} can't be more than {MAX_STRING_LENGTH}"
+                ));
+            }
+        }
+
+        if let Some(max_length) = self.max_length {
+            if max_length == 0 || max_length > MAX_STRING_LENGTH {
+                return Err(format!(
+                    "max_length of option {name} has to be 1-{MAX_STRING_LENGTH}"
+                ));
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.min_length, self.max_length) {
+            if min > max {
+                return Err(format!(
+                    "min_length of option {name} is greater than its max_length"
+                ));
+            }
+        }
+
+        if !self.channel_types.is_empty() && self.option_type != OptionType::Channel {
+            return Err(format!(
+                "option {name} can't have channel_types, only channel options can"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        validate_name, ApplicationCommandOption, ApplicationCommandOptionChoice, Localizations,
+        OptionType,
+    };
+
+    fn option(option_type: OptionType) -> ApplicationCommandOption {
+        ApplicationCommandOption::new(
+            String::from("option"),
+            String::from("description"),
+            option_type,
+            false,
+        )
+    }
+
+    #[test]
+    fn bounds_have_to_be_ordered() {
+        assert!(option(OptionType::Integer)
+            .set_min_value(1)
+            .set_max_value(1)
+            .validate()
+            .is_ok());
+        assert!(option(OptionType::Integer)
+            .set_min_value(2)
+            .set_max_value(1)
+            .validate()
+            .is_err());
+        assert!(option(OptionType::Number)
+            .set_min_value(1)
+            .set_max_number(0.5)
+            .validate()
+            .is_err());
+        assert!(option(OptionType::String)
+            .set_min_length(2)
+            .set_max_length(1)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn limits_only_on_matching_option_types() {
+        assert!(option(OptionType::String)
+            .set_min_length(1)
+            .set_max_length(100)
+            .validate()
+            .is_ok());
+        assert!(option(OptionType::Integer)
+            .set_max_length(100)
+            .validate()
+            .is_err());
+        assert!(option(OptionType::String)
+            .set_min_value(1)
+            .validate()
+            .is_err());
+        assert!(option(OptionType::Integer)
+            .set_max_number(1.5)
+            .validate()
+            .is_err());
+        assert!(option(OptionType::Channel)
+            .set_channel_types(vec![2])
+            .validate()
+            .is_ok());
+        assert!(option(OptionType::String)
+            .set_channel_types(vec![2])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn autocomplete_only_on_string_integer_and_number() {
+        for option_type in [OptionType::String, OptionType::Integer, OptionType::Number] {
+            assert!(option(option_type)
+                .set_autocomplete(true)
+                .validate()
+                .is_ok());
+        }
+
+        for option_type in [OptionType::Boolean, OptionType::User, OptionType::Channel] {
+            assert!(option(option_type)
+                .set_autocomplete(true)
+                .validate()
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn string_choice_values_are_limited() {
+        let mut valid = option(OptionType::String);
+
+        valid.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("choice"),
+            "a".repeat(100),
+        ));
+
+        assert!(valid.validate().is_ok());
+
+        let mut invalid = option(OptionType::String);
+
+        invalid.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("choice"),
+            "a".repeat(101),
+        ));
+
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn names_use_discords_charset() {
+        for name in [
+            "play",
+            "skip-to",
+            "play_next",
+            "hraj2",
+            "přeskočit",
+            "चलाओ",
+            "เล่น",
+        ] {
+            assert!(validate_name(name, None).is_ok(), "{name}");
+        }
+
+        for name in [
+            "",
+            "play next",
+            "play.next",
+            "what?",
+            "Play",
+            &"a".repeat(33),
+        ] {
+            assert!(validate_name(name, None).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn localized_names_are_validated() {
+        let localized = Localizations::from([(String::from("cs"), String::from("hraj!"))]);
+
+        assert!(validate_name("play", Some(&localized)).is_err());
+    }
+
+    #[test]
+    fn choice_names_are_limited() {
+        let mut valid = option(OptionType::String);
+
+        valid.add_choice(
+            ApplicationCommandOptionChoice::new("a".repeat(100), String::from("value"))
+                .set_name_loc("cs", "Něco s mezerou"),
+        );
+
+        assert!(valid.validate().is_ok());
+
+        let mut invalid = option(OptionType::String);
+
+        invalid.add_choice(
+            ApplicationCommandOptionChoice::new(String::from("choice"), String::from("value"))
+                .set_name_loc("cs", &"a".repeat(101)),
+        );
+
+        assert!(invalid.validate().is_err());
+    }
 }
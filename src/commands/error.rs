@@ -0,0 +1,86 @@
+use std::fmt;
+
+use rand::Rng;
+
+use crate::client::events::{Interaction, InteractionCallbackData};
+
+#[derive(Debug)]
+pub enum CommandError {
+    /// Something the member can fix, e.g. not being in a voice channel. The message is shown as is.
+    User(String),
+    /// A failure on our side (lavalink, Discord API), the member only gets an id to report.
+    Internal(String),
+}
+
+impl CommandError {
+    pub fn user(message: impl Into<String>) -> Self {
+        Self::User(message.into())
+    }
+
+    /// Replaces the deferred response with an ephemeral message, so only the
+    /// member who used the command sees that it failed.
+    pub async fn report(&self, interaction: &Interaction) {
+        let content = match self {
+            Self::User(message) => message.clone(),
+            Self::Internal(message) => {
+                let id = format!("{:06x}", rand::thread_rng().gen_range(0..0x1000000));
+
+                println!("Error {id} handling interaction: {message}");
+
+                interaction.text_with("errors.internal", &[("id", &id)])
+            }
+        };
+
+        // autocomplete can't be answered with a message
+        if interaction.interaction_type == 4 {
+            return;
+        }
+
+        if interaction.delete_original().await.is_err() {
+            // nothing was acknowledged yet, e.g. a failed modal
+            if let Err(err) = interaction
+                .respond(
+                    InteractionCallbackData::new()
+                        .set_content(&content)
+                        .set_flags(64),
+                )
+                .await
+            {
+                println!("Error sending error message: {err:?}");
+            }
+
+            return;
+        }
+
+        if let Err(err) = interaction
+            .create_followup(
+                InteractionCallbackData::new()
+                    .set_content(&content)
+                    .set_flags(64),
+            )
+            .await
+        {
+            println!("Error sending error message: {err:?}");
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User(message) | Self::Internal(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::Internal(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::Internal(String::from(message))
+    }
+}
@@ -1,15 +1,26 @@
-use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
 
 pub mod builder;
+mod error;
+mod panel;
+mod playlist;
+mod queue;
+mod search;
+
+pub use error::CommandError;
+pub use panel::update_panel;
 
 use crate::{
     client::{
-        events::{self, Interaction, InteractionCallbackData},
-        LavalinkClient,
+        events::{self, Interaction, InteractionCallbackData, PERMISSION_MANAGE_GUILD},
+        LavalinkClient, LoadType, LoopMode, Player, Track,
     },
     commands::builder::{
-        ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionChoice,
+        ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionChoice, OptionType,
     },
+    locale::Locales,
     toulen::{get_download_url, get_players},
 };
 
@@ -25,39 +36,209 @@ fn format_time(time: u64) -> String {
     }
 }
 
+fn progress_bar(position: u64, length: u64) -> String {
+    const WIDTH: u64 = 20;
+
+    let filled = (position.min(length) * WIDTH)
+        .checked_div(length)
+        .unwrap_or(0)
+        .min(WIDTH - 1);
+
+    (0..WIDTH)
+        .map(|i| if i == filled { '🔘' } else { '▬' })
+        .collect()
+}
+
+/// Reverse of `format_time`, accepts `ss`, `mm:ss` and `hh:mm:ss`.
+fn parse_time(time: &str) -> Option<u64> {
+    let parts = time
+        .trim()
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    let seconds = match parts.as_slice() {
+        [seconds] => *seconds,
+        [minutes, seconds] if *seconds < 60 => minutes.checked_mul(60)?.checked_add(*seconds)?,
+        [hours, minutes, seconds] if *minutes < 60 && *seconds < 60 => hours
+            .checked_mul(3600)?
+            .checked_add(minutes * 60 + seconds)?,
+        _ => return None,
+    };
+
+    Some(seconds)
+}
+
+enum Seek {
+    To(u64),
+    Forward(u64),
+    Rewind(u64),
+}
+
+/// The player of the interaction's guild, as long as the member is in the same
+/// voice channel. Otherwise a user error telling the member why not.
+async fn member_player<'m>(
+    interaction: &Interaction,
+    manager: &'m mut LavalinkClient,
+) -> Result<&'m mut Player, CommandError> {
+    let guild_id = &interaction.guild_id;
+    let channel_id = match interaction.member.get_voice_channel() {
+        Some(channel_id) => channel_id,
+        None => return Err(CommandError::user(interaction.text("errors.not_in_voice"))),
+    };
+
+    let player = match manager.get_player_mut(guild_id) {
+        Some(player) => player,
+        None => {
+            return Err(CommandError::user(
+                interaction.text("errors.nothing_playing"),
+            ))
+        }
+    };
+
+    if player.channel_id != *channel_id {
+        return Err(CommandError::user(
+            interaction.text("errors.not_same_voice"),
+        ));
+    }
+
+    Ok(player)
+}
+
+/// Like `member_player`, but joins the member's voice channel when the guild
+/// has no player yet.
+async fn member_or_new_player<'m>(
+    interaction: &Interaction,
+    manager: &'m mut LavalinkClient,
+) -> Result<&'m mut Player, CommandError> {
+    if manager.get_player(&interaction.guild_id).is_none() {
+        let channel_id = match interaction.member.get_voice_channel() {
+            Some(channel_id) => channel_id,
+            None => return Err(CommandError::user(interaction.text("errors.not_in_voice"))),
+        };
+
+        return Ok(manager.join(&interaction.guild_id, channel_id)?);
+    }
+
+    member_player(interaction, manager).await
+}
+
 pub async fn command_handler(
     interaction: &Interaction,
     manager: &mut LavalinkClient,
-) -> Result<(), String> {
-    match interaction.get_name() {
-        Some("ts") => {
-            if let Err(_) = interaction.ack(64).await {
-                // println!("Error acknowledging interaction: {err:?}");
-            }
-        }
-        _ => {
-            if let Err(err) = interaction.ack(0).await {
-                println!("Error acknowledging interaction: {err:?}");
-            }
+) -> Result<(), CommandError> {
+    if interaction.interaction_type == 4 {
+        return autocomplete_handler(interaction, manager).await;
+    }
+
+    let result = match interaction.interaction_type {
+        3 => component_handler(interaction, manager).await,
+        5 => modal_handler(interaction, manager).await,
+        _ => slash_command_handler(interaction, manager).await,
+    };
+
+    let player = match manager.get_player_mut(&interaction.guild_id) {
+        Some(player) => player,
+        None => return result,
+    };
+
+    if player.text_channel_id.is_none() {
+        player.text_channel_id = Some(interaction.channel_id.clone());
+        player.locale = Some(String::from(interaction.get_locale()));
+    }
+
+    // keep the panel in sync with changes made by commands
+    if player.panel_message_id.is_some() {
+        if let Err(err) = update_panel(manager, &interaction.guild_id).await {
+            println!("Error updating player panel: {err:?}");
         }
     }
 
-    match interaction.get_name() {
-        Some("join") => join_channel(interaction, manager).await,
-        Some("leave") => leave_channel(interaction, manager).await,
-        Some("play") => play_track(interaction, manager).await,
-        Some("pause") => pause_track(interaction, manager).await,
-        Some("skip") => skip_track(interaction, manager).await,
-        Some("ts") => toulen(interaction).await,
-        _ => Ok(()),
+    result
+}
+
+async fn ack(interaction: &Interaction, flags: u32) {
+    if let Err(err) = interaction.ack(flags).await {
+        println!("Error acknowledging interaction: {err:?}");
+    }
+}
+
+async fn slash_command_handler(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let name = interaction.get_name().ok_or("missing command name")?;
+
+    let command = Commands::registry()
+        .get(name)
+        .ok_or_else(|| format!("unknown command {name}"))?;
+
+    match command.ack {
+        Ack::Public => ack(interaction, 0).await,
+        Ack::Ephemeral => ack(interaction, 64).await,
+        Ack::Manual => (),
     }
+
+    (command.handler)(interaction, manager).await
 }
 
-async fn toulen(interaction: &Interaction) -> Result<(), String> {
-    if let Err(err) = interaction.ack(64).await {
+async fn modal_handler(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    if let Err(err) = interaction.ack(0).await {
         println!("Error acknowledging interaction: {err:?}");
     }
 
+    match interaction.get_custom_id().ok_or("missing custom_id")? {
+        "playlist:save" => playlist::save_submit(interaction, manager).await,
+        "playlist:import" => playlist::import_submit(interaction, manager).await,
+        custom_id => Err(format!("unknown modal {custom_id}").into()),
+    }
+}
+
+async fn autocomplete_handler(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let autocomplete = interaction
+        .get_name()
+        .and_then(|name| Commands::registry().get(name))
+        .and_then(|command| command.autocomplete);
+
+    match autocomplete {
+        Some(autocomplete) => autocomplete(interaction, manager).await,
+        None => Ok(interaction.autocomplete(vec![]).await?),
+    }
+}
+
+async fn component_handler(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let custom_id = interaction.get_custom_id().ok_or("missing custom_id")?;
+
+    // the search results get replaced by the picked song, panel buttons reply
+    // on their own so the panel itself stays untouched
+    let ack = match custom_id.split_once(':') {
+        Some(("player", _)) => interaction.ack(64).await,
+        _ => interaction.ack_update().await,
+    };
+
+    if let Err(err) = ack {
+        println!("Error acknowledging interaction: {err:?}");
+    }
+
+    match custom_id.split_once(':') {
+        Some(("search", _)) => search::pick_track(interaction, manager).await,
+        Some(("player", action)) => panel::panel_button(interaction, manager, action).await,
+        _ => Err(format!("unknown component {custom_id}").into()),
+    }
+}
+
+async fn toulen(interaction: &Interaction) -> Result<(), CommandError> {
+    ack(interaction, 64).await;
+
     let name = interaction
         .data
         .as_ref()
@@ -68,11 +249,11 @@ async fn toulen(interaction: &Interaction) -> Result<(), String> {
     match name {
         Some("download") => ts_download(interaction).await,
         Some("leaderboard") => ts_leaderboard(interaction).await,
-        _ => Err(String::from("unknown subcommand")),
+        _ => Err("unknown subcommand".into()),
     }
 }
 
-async fn ts_download(interaction: &Interaction) -> Result<(), String> {
+async fn ts_download(interaction: &Interaction) -> Result<(), CommandError> {
     let url = get_download_url().await?;
 
     let url = url.replace(".msi.zip", ".msi");
@@ -83,13 +264,13 @@ async fn ts_download(interaction: &Interaction) -> Result<(), String> {
         .set_color(0xf17c00);
 
     interaction
-        .create_message(InteractionCallbackData::new().add_embed(embed))
+        .edit_original(InteractionCallbackData::new().add_embed(embed))
         .await?;
 
     Ok(())
 }
 
-async fn ts_leaderboard(interaction: &Interaction) -> Result<(), String> {
+async fn ts_leaderboard(interaction: &Interaction) -> Result<(), CommandError> {
     let players = get_players().await?;
 
     let mut leaderboard = String::new();
@@ -106,7 +287,7 @@ async fn ts_leaderboard(interaction: &Interaction) -> Result<(), String> {
         .set_color(0xf17c00);
 
     interaction
-        .create_message(InteractionCallbackData::new().add_embed(embed))
+        .edit_original(InteractionCallbackData::new().add_embed(embed))
         .await?;
 
     Ok(())
@@ -115,136 +296,53 @@ async fn ts_leaderboard(interaction: &Interaction) -> Result<(), String> {
 async fn join_channel(
     interaction: &Interaction,
     manager: &mut LavalinkClient,
-) -> Result<(), String> {
-    if interaction.member.voice.is_none() {
-        interaction
-            .create_message(InteractionCallbackData::new().set_content("musíš být v roomce"))
-            .await?;
-    } else {
-        let guild_id = &interaction.guild_id;
-        let channel_id = match interaction.member.get_voice_channel() {
-            Some(channel_id) => channel_id,
-            None => {
-                interaction
-                    .create_message(
-                        InteractionCallbackData::new().set_content("musíš být v roomce"),
-                    )
-                    .await?;
-
-                return Ok(());
-            }
-        };
-
-        if let Some(player) = manager.get_player(guild_id) {
-            let channel_id = &player.channel_id;
-
-            interaction
-                .create_message(
-                    InteractionCallbackData::new()
-                        .set_content(&format!("už je připojen v <#{channel_id}>")),
-                )
-                .await?;
-
-            return Ok(());
-        };
+) -> Result<(), CommandError> {
+    let joined = manager.get_player(&interaction.guild_id).is_some();
 
-        manager.join(guild_id, channel_id)?;
+    let channel_id = &member_or_new_player(interaction, manager).await?.channel_id;
 
-        interaction
-            .create_message(
-                InteractionCallbackData::new()
-                    .set_content(&format!("připojeno do <#{channel_id}>")),
-            )
-            .await?;
+    if joined {
+        return Err(CommandError::user(
+            interaction.text_with("join.already_joined", &[("channel", channel_id)]),
+        ));
     }
 
+    interaction
+        .edit_original(
+            InteractionCallbackData::new()
+                .set_content(&interaction.text_with("join.joined", &[("channel", channel_id)])),
+        )
+        .await?;
+
     Ok(())
 }
 
 async fn leave_channel(
     interaction: &Interaction,
     manager: &mut LavalinkClient,
-) -> Result<(), String> {
-    let guild_id = &interaction.guild_id;
-    let channel_id = match interaction.member.get_voice_channel() {
-        Some(channel_id) => channel_id,
-        None => {
-            interaction
-                .create_message(InteractionCallbackData::new().set_content("musíš být v roomce"))
-                .await?;
+) -> Result<(), CommandError> {
+    member_player(interaction, manager).await?;
 
-            return Ok(());
-        }
-    };
-
-    let player = match manager.get_player(guild_id) {
-        Some(player) => player,
-        None => {
-            interaction
-                .create_message(InteractionCallbackData::new().set_content("nic nehraje"))
-                .await?;
-
-            return Ok(());
-        }
-    };
-
-    if player.channel_id != *channel_id {
-        interaction
-            .create_message(
-                InteractionCallbackData::new().set_content("musíš být ve stejné roomce"),
-            )
-            .await?;
-
-        return Ok(());
-    }
-
-    manager.destroy_player(guild_id)?;
+    manager.destroy_player(&interaction.guild_id)?;
 
     interaction
-        .create_message(InteractionCallbackData::new().set_content("odpojeno"))
+        .edit_original(InteractionCallbackData::new().set_content(&interaction.text("join.left")))
         .await?;
 
     Ok(())
 }
 
-async fn play_track(interaction: &Interaction, manager: &mut LavalinkClient) -> Result<(), String> {
-    let guild_id = &interaction.guild_id;
-    let channel_id = match interaction.member.get_voice_channel() {
-        Some(channel_id) => channel_id,
-        None => {
-            interaction
-                .create_message(InteractionCallbackData::new().set_content("musíš být v roomce"))
-                .await?;
-
-            return Ok(());
-        }
-    };
-
-    let player = match manager.get_player_mut(guild_id) {
-        Some(player) => player,
-        None => manager.join(guild_id, channel_id)?,
-    };
-
-    if player.channel_id != *channel_id {
-        interaction
-            .create_message(
-                InteractionCallbackData::new().set_content("musíš být ve stejné roomce"),
-            )
-            .await?;
-
-        return Ok(());
-    }
-
-    let content = interaction.get_value("query").ok_or("missing query")?;
+async fn play_track(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let player = member_or_new_player(interaction, manager).await?;
 
-    let content = match content {
-        Value::String(content) => content,
-        _ => return Err(String::from("missing query")),
-    };
+    let content = interaction.get_string("query")?;
 
-    let platform = match interaction.get_value("platform") {
-        Some(Value::String(platform)) => Some(platform.as_str()),
-        _ => {
+    let platform = match interaction.get_string("platform") {
+        Ok(platform) => Some(platform),
+        Err(_) => {
             if content.starts_with("https://") || content.starts_with("http://") {
                 None
             } else {
@@ -256,14 +354,12 @@ async fn play_track(interaction: &Interaction, manager: &mut LavalinkClient) ->
     let result = player.search(content, platform).await?;
 
     if result.tracks.is_empty() {
-        interaction
-            .create_message(InteractionCallbackData::new().set_content("nic nebylo nenalezeno"))
-            .await?;
-
-        return Ok(());
+        return Err(CommandError::user(interaction.text("errors.nothing_found")));
     }
 
-    if result.load_type == "PLAYLIST_LOADED" {
+    let requester = interaction.member.display_name();
+
+    if result.load_type == LoadType::PlaylistLoaded {
         let playlist_tracks_num = result.tracks.len();
         let playlist_len = result.tracks.iter().map(|t| t.info.length).sum::<u64>();
 
@@ -280,8 +376,9 @@ async fn play_track(interaction: &Interaction, manager: &mut LavalinkClient) ->
         let lenght = format_time(playlist_len / 1000);
 
         let embed = events::Embed::new()
-            .set_description(format!(
-                "**[{name}]()**\npřidáno {playlist_tracks_num} songů do fronty" // result.playlist_info.unwrap().selected_track.unwrap()
+            .set_description(interaction.text_with(
+                "track.playlist_added",
+                &[("name", name), ("count", &playlist_tracks_num)],
             ))
             .set_color(0x0080f0)
             .set_thumbnail(events::EmbedThumbnail {
@@ -293,160 +390,341 @@ async fn play_track(interaction: &Interaction, manager: &mut LavalinkClient) ->
                 width: None,
             })
             .set_footer(events::EmbedFooter {
-                text: format!("Trvání: {lenght}"),
+                text: interaction.text_with(
+                    "track.footer",
+                    &[("length", &lenght), ("requester", &requester)],
+                ),
                 icon_url: None,
                 proxy_icon_url: None,
             });
 
         interaction
-            .create_message(InteractionCallbackData::new().add_embed(embed))
+            .edit_original(InteractionCallbackData::new().add_embed(embed))
             .await?;
 
         for track in result.tracks {
-            player.play(track);
+            player.play(track.set_requester(&interaction.member));
         }
     } else if let Some(track) = result.tracks.into_iter().next() {
-        let (title, uri) = (&track.info.title, &track.info.uri);
-
-        let identifier = &track.info.identifier;
-
-        let lenght = format_time(track.info.length / 1000);
-
-        let embed = events::Embed::new()
-            .set_description(format!("**[{title}]({uri})**\nbylo přidáno do fronty"))
-            .set_color(0x0080f0)
-            .set_thumbnail(events::EmbedThumbnail {
-                url: Some(format!(
-                    "https://i.ytimg.com/vi/{identifier}/maxresdefault.jpg"
-                )),
-                proxy_url: None,
-                height: None,
-                width: None,
-            })
-            .set_footer(events::EmbedFooter {
-                text: format!("Trváni: {lenght}"),
-                icon_url: None,
-                proxy_icon_url: None,
-            });
-
         interaction
-            .create_message(InteractionCallbackData::new().add_embed(embed))
+            .edit_original(
+                InteractionCallbackData::new().add_embed(queued_embed(interaction, &track)),
+            )
             .await?;
 
-        player.play(track);
+        player.play(track.set_requester(&interaction.member));
     }
 
     Ok(())
 }
 
+fn queued_embed(interaction: &Interaction, track: &Track) -> events::Embed {
+    let link = track.info.link();
+
+    let requester = interaction.member.display_name();
+
+    let identifier = &track.info.identifier;
+
+    let lenght = format_time(track.info.length / 1000);
+
+    events::Embed::new()
+        .set_description(interaction.text_with("track.added", &[("track", &link)]))
+        .set_color(0x0080f0)
+        .set_thumbnail(events::EmbedThumbnail {
+            url: Some(format!(
+                "https://i.ytimg.com/vi/{identifier}/maxresdefault.jpg"
+            )),
+            proxy_url: None,
+            height: None,
+            width: None,
+        })
+        .set_footer(events::EmbedFooter {
+            text: interaction.text_with(
+                "track.footer",
+                &[("length", &lenght), ("requester", &requester)],
+            ),
+            icon_url: None,
+            proxy_icon_url: None,
+        })
+}
+
 async fn pause_track(
     interaction: &Interaction,
     manager: &mut LavalinkClient,
-) -> Result<(), String> {
-    let guild_id = &interaction.guild_id;
-    let channel_id = match interaction.member.get_voice_channel() {
-        Some(channel_id) => channel_id,
-        None => {
-            interaction
-                .create_message(InteractionCallbackData::new().set_content("musíš být v roomce"))
-                .await?;
+) -> Result<(), CommandError> {
+    let player = member_player(interaction, manager).await?;
 
-            return Ok(());
+    let paused = interaction.get_bool("paused")?;
+
+    player.pause(paused);
+
+    interaction
+        .edit_original(
+            InteractionCallbackData::new().set_content(&interaction.text(if paused {
+                "pause.paused"
+            } else {
+                "pause.resumed"
+            })),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn skip_track(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let player = member_player(interaction, manager).await?;
+
+    let track = match player.skip() {
+        Some(track) => track,
+        None => {
+            return Err(CommandError::user(
+                interaction.text("errors.nothing_playing"),
+            ))
         }
     };
 
-    let player = match manager.get_player_mut(guild_id) {
-        Some(player) => player,
-        None => {
-            interaction
-                .create_message(InteractionCallbackData::new().set_content("musíš být v roomce"))
-                .await?;
+    let link = track.info.link();
 
-            return Ok(());
-        }
+    let identifier = &track.info.identifier;
+
+    let embed = events::Embed::new()
+        .set_description(interaction.text_with("track.skipped", &[("track", &link)]))
+        .set_color(0x0080f0)
+        .set_thumbnail(events::EmbedThumbnail {
+            url: Some(format!(
+                "https://i.ytimg.com/vi/{identifier}/maxresdefault.jpg"
+            )),
+            proxy_url: None,
+            height: None,
+            width: None,
+        });
+
+    interaction
+        .edit_original(InteractionCallbackData::new().add_embed(embed))
+        .await?;
+
+    Ok(())
+}
+async fn seek_to(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let position = interaction.get_string("position")?;
+
+    // typed by members, so anything that doesn't fit in milliseconds is invalid too
+    let position = match parse_time(position).and_then(|p| p.checked_mul(1000)) {
+        Some(position) => position,
+        None => return Err(CommandError::user(interaction.text("seek.invalid_time"))),
     };
 
-    if player.channel_id != *channel_id {
-        interaction
-            .create_message(
-                InteractionCallbackData::new().set_content("musíš být ve stejné roomce"),
-            )
-            .await?;
+    seek_track(interaction, manager, Seek::To(position)).await
+}
 
-        return Ok(());
-    }
+async fn seek_forward(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let seconds = interaction.get_integer("seconds")?;
 
-    let paused = interaction.get_value("paused").ok_or("missing query")?;
+    let seconds = u64::try_from(seconds).map_err(|_| format!("seconds {seconds} out of range"))?;
 
-    let paused = match paused {
-        Value::Bool(paused) => paused,
-        _ => return Err(String::from("missing query")),
+    seek_track(
+        interaction,
+        manager,
+        Seek::Forward(seconds.saturating_mul(1000)),
+    )
+    .await
+}
+
+async fn seek_rewind(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let seconds = interaction.get_integer("seconds")?;
+
+    let seconds = u64::try_from(seconds).map_err(|_| format!("seconds {seconds} out of range"))?;
+
+    seek_track(
+        interaction,
+        manager,
+        Seek::Rewind(seconds.saturating_mul(1000)),
+    )
+    .await
+}
+
+async fn seek_track(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+    seek: Seek,
+) -> Result<(), CommandError> {
+    let player = member_player(interaction, manager).await?;
+
+    let (length, seekable) = match player.queue.first() {
+        Some(track) => (
+            track.info.length,
+            track.info.is_seekable && !track.info.is_stream,
+        ),
+        None => {
+            return Err(CommandError::user(
+                interaction.text("errors.nothing_playing"),
+            ))
+        }
     };
 
-    player.pause(*paused);
+    if !seekable {
+        return Err(CommandError::user(interaction.text("seek.not_seekable")));
+    }
+
+    let position = match seek {
+        Seek::To(position) => position,
+        Seek::Forward(offset) => player.position().saturating_add(offset),
+        Seek::Rewind(offset) => player.position().saturating_sub(offset),
+    };
+
+    if position >= length {
+        let length = format_time(length / 1000);
+
+        return Err(CommandError::user(
+            interaction.text_with("seek.too_far", &[("length", &length)]),
+        ));
+    }
+
+    player.seek(position)?;
+
+    let (position, length) = (format_time(position / 1000), format_time(length / 1000));
 
     interaction
-        .create_message(InteractionCallbackData::new().set_content(if *paused {
-            "přehrávání pozastaveno"
-        } else {
-            "pokračování v přehrávání"
-        }))
+        .edit_original(InteractionCallbackData::new().set_content(
+            &interaction.text_with("seek.done", &[("position", &position), ("length", &length)]),
+        ))
         .await?;
 
     Ok(())
 }
 
-async fn skip_track(interaction: &Interaction, manager: &mut LavalinkClient) -> Result<(), String> {
+async fn set_volume(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
     let guild_id = &interaction.guild_id;
-    let channel_id = match interaction.member.get_voice_channel() {
-        Some(channel_id) => channel_id,
-        None => {
-            interaction
-                .create_message(InteractionCallbackData::new().set_content("musíš být v roomce"))
-                .await?;
 
-            return Ok(());
-        }
-    };
+    let volume = interaction.get_integer("level")?;
 
-    let player = match manager.get_player_mut(guild_id) {
-        Some(player) => player,
-        None => {
-            interaction
-                .create_message(InteractionCallbackData::new().set_content("musíš být v roomce"))
-                .await?;
+    let volume = u8::try_from(volume).map_err(|_| format!("volume {volume} out of range"))?;
 
-            return Ok(());
-        }
-    };
+    let default = interaction.get_bool("default").unwrap_or(false);
+
+    // the default is for the whole server, so only who manages it may change it
+    if default && !interaction.member.has_permission(PERMISSION_MANAGE_GUILD) {
+        return Err(CommandError::user(
+            interaction.text("volume.default_forbidden"),
+        ));
+    }
+
+    if default && manager.get_player(guild_id).is_none() {
+        manager.settings.guild_mut(guild_id).volume = volume;
+
+        manager.settings.save().await?;
 
-    if player.channel_id != *channel_id {
         interaction
-            .create_message(
-                InteractionCallbackData::new().set_content("musíš být ve stejné roomce"),
+            .edit_original(
+                InteractionCallbackData::new().set_content(
+                    &interaction.text_with("volume.default_set", &[("volume", &volume)]),
+                ),
             )
             .await?;
 
         return Ok(());
     }
 
-    let track = match player.skip() {
-        Some(track) => track,
-        None => {
-            interaction
-                .create_message(InteractionCallbackData::new().set_content("nic nehraje"))
-                .await?;
+    let player = member_player(interaction, manager).await?;
 
-            return Ok(());
-        }
+    player.set_volume(volume);
+
+    if default {
+        manager.settings.guild_mut(guild_id).volume = volume;
+
+        manager.settings.save().await?;
+    }
+
+    interaction
+        .edit_original(
+            InteractionCallbackData::new()
+                .set_content(&interaction.text_with("volume.set", &[("volume", &volume)])),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn set_loop(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let player = member_player(interaction, manager).await?;
+
+    let (loop_mode, content) = match interaction.get_string("mode")? {
+        "off" => (LoopMode::Off, "loop.off"),
+        "track" => (LoopMode::Track, "loop.track"),
+        "queue" => (LoopMode::Queue, "loop.queue"),
+        mode => return Err(format!("unknown loop mode {mode}").into()),
     };
 
-    let (title, uri) = (&track.info.title, &track.info.uri);
+    player.loop_mode = loop_mode;
+
+    interaction
+        .edit_original(InteractionCallbackData::new().set_content(&interaction.text(content)))
+        .await?;
+
+    Ok(())
+}
+
+fn now_playing_embed(interaction: &Interaction, player: &Player) -> Option<events::Embed> {
+    let track = player.queue.first()?;
+
+    let (link, author) = (track.info.link(), &track.info.author);
 
     let identifier = &track.info.identifier;
 
+    let progress = if track.info.is_stream {
+        String::from("🔴 LIVE")
+    } else {
+        let position = player.position();
+
+        let bar = progress_bar(position, track.info.length);
+
+        let (elapsed, total) = (
+            format_time(position / 1000),
+            format_time(track.info.length / 1000),
+        );
+
+        format!("`{elapsed}` {bar} `{total}`")
+    };
+
+    let state = if player.paused { "⏸️ " } else { "" };
+
+    let requester = match &track.requester {
+        Some(requester) => {
+            let (user_id, added_at) = (&requester.user_id, requester.added_at);
+
+            let requester = interaction.text_with(
+                "now_playing.requester",
+                &[("user", user_id), ("added_at", &added_at)],
+            );
+
+            format!("\n\n{requester}")
+        }
+        None => String::new(),
+    };
+
     let embed = events::Embed::new()
-        .set_description(format!("**[{title}]({uri})**\nbylo přeskočeno"))
+        .set_title(interaction.text("now_playing.title"))
+        .set_description(format!(
+            "**{link}**\n{author}\n\n{state}{progress}{requester}"
+        ))
         .set_color(0x0080f0)
         .set_thumbnail(events::EmbedThumbnail {
             url: Some(format!(
@@ -457,73 +735,161 @@ async fn skip_track(interaction: &Interaction, manager: &mut LavalinkClient) ->
             width: None,
         });
 
+    Some(embed)
+}
+
+async fn now_playing(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let embed = match manager
+        .get_player(&interaction.guild_id)
+        .and_then(|player| now_playing_embed(interaction, player))
+    {
+        Some(embed) => embed,
+        None => {
+            return Err(CommandError::user(
+                interaction.text("errors.nothing_playing"),
+            ))
+        }
+    };
+
     interaction
-        .create_message(InteractionCallbackData::new().add_embed(embed))
+        .edit_original(InteractionCallbackData::new().add_embed(embed))
         .await?;
 
     Ok(())
 }
+
+pub type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Result<(), CommandError>> + 'a>>;
+
+pub type Handler = for<'a> fn(&'a Interaction, &'a mut LavalinkClient) -> HandlerFuture<'a>;
+
+/// How the interaction is acknowledged before the handler runs.
+#[derive(Debug, Clone, Copy)]
+pub enum Ack {
+    Public,
+    Ephemeral,
+    /// The handler responds itself, e.g. with a modal.
+    Manual,
+}
+
+/// A command definition together with the code that handles it.
+pub struct Command {
+    pub definition: ApplicationCommand,
+    pub ack: Ack,
+    handler: Handler,
+    autocomplete: Option<Handler>,
+}
+
+impl Command {
+    pub fn new(definition: ApplicationCommand, handler: Handler) -> Self {
+        Self {
+            definition,
+            ack: Ack::Public,
+            handler,
+            autocomplete: None,
+        }
+    }
+
+    pub fn set_ack(mut self, ack: Ack) -> Self {
+        self.ack = ack;
+        self
+    }
+
+    pub fn set_autocomplete(mut self, autocomplete: Handler) -> Self {
+        self.autocomplete = Some(autocomplete);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.definition.name
+    }
+}
+
 pub struct Commands {
-    pub commands: Vec<ApplicationCommand>,
+    pub commands: Vec<Command>,
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Commands {
+    /// Every command the bot handles, no matter where it is registered.
+    pub fn registry() -> &'static Commands {
+        static REGISTRY: OnceLock<Commands> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| {
+            let mut commands = Commands::new();
+
+            commands.commands.extend(Commands::toulen().commands);
+
+            commands
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.iter().find(|command| command.name() == name)
+    }
+
+    /// The definitions to register with Discord.
+    pub fn definitions(&self) -> Vec<ApplicationCommand> {
+        self.commands
+            .iter()
+            .map(|command| command.definition.clone())
+            .collect()
+    }
+
     pub fn new() -> Self {
         let join_cmd = ApplicationCommand::new(
             1,
             String::from("join"),
             String::from("joins the voice channel"),
-        )
-        .set_name_loc("připojit")
-        .set_desc_loc("připojí bota do roomky");
+        );
 
         let leave_cmd = ApplicationCommand::new(
             1,
             String::from("leave"),
             String::from("leaves the voice channel"),
-        )
-        .set_name_loc("odpojit")
-        .set_desc_loc("odpojí bota z roomky");
+        );
 
         let mut play_cmd =
-            ApplicationCommand::new(1, String::from("play"), String::from("plays a song"))
-                .set_name_loc("hraj")
-                .set_desc_loc("přehraje song");
+            ApplicationCommand::new(1, String::from("play"), String::from("plays a song"));
 
         play_cmd.add_option(
             ApplicationCommandOption::new(
                 String::from("query"),
                 String::from("song to play"),
-                3,
+                OptionType::String,
                 true,
             )
-            .set_name_loc("vyhledávání")
-            .set_desc_loc("song k přehrávání"),
+            .set_autocomplete(true),
         );
 
         let mut platform_choice = ApplicationCommandOption::new(
             String::from("platform"),
             String::from("platform to search on"),
-            3,
+            OptionType::String,
             false,
-        )
-        .set_name_loc("platforma")
-        .set_desc_loc("platforma pro vyhledávání");
+        );
 
-        platform_choice.add_choice(ApplicationCommandOptionChoice {
-            name: String::from("YouTube"),
-            value: String::from("ytsearch"),
-        });
+        platform_choice.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("YouTube"),
+            String::from("ytsearch"),
+        ));
 
-        platform_choice.add_choice(ApplicationCommandOptionChoice {
-            name: String::from("YouTube Music"),
-            value: String::from("ytmsearch"),
-        });
+        platform_choice.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("YouTube Music"),
+            String::from("ytmsearch"),
+        ));
 
-        platform_choice.add_choice(ApplicationCommandOptionChoice {
-            name: String::from("SoundCloud"),
-            value: String::from("scsearch"),
-        });
+        platform_choice.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("SoundCloud"),
+            String::from("scsearch"),
+        ));
 
         play_cmd.add_option(platform_choice);
 
@@ -531,66 +897,392 @@ impl Commands {
             1,
             String::from("pause"),
             String::from("pauses the current song"),
-        )
-        .set_name_loc("pauza")
-        .set_desc_loc("pauzuje přehrávání");
+        );
 
-        pause_cmd.add_option(
+        pause_cmd.add_option(ApplicationCommandOption::new(
+            String::from("paused"),
+            String::from("pause or unpause"),
+            OptionType::Boolean,
+            true,
+        ));
+
+        let skip_cmd = ApplicationCommand::new(
+            1,
+            String::from("skip"),
+            String::from("skips the current song"),
+        );
+
+        let mut seek_cmd = ApplicationCommand::new(
+            1,
+            String::from("seek"),
+            String::from("seeks to a position in the current song"),
+        );
+
+        seek_cmd.add_option(ApplicationCommandOption::new(
+            String::from("position"),
+            String::from("position as mm:ss or hh:mm:ss"),
+            OptionType::String,
+            true,
+        ));
+
+        let mut forward_cmd = ApplicationCommand::new(
+            1,
+            String::from("forward"),
+            String::from("skips forward in the current song"),
+        );
+
+        forward_cmd.add_option(
             ApplicationCommandOption::new(
-                String::from("paused"),
-                String::from("pause or unpause"),
-                5,
+                String::from("seconds"),
+                String::from("how many seconds to skip"),
+                OptionType::Integer,
                 true,
             )
-            .set_name_loc("pauza")
-            .set_desc_loc("pauza nebo pokračování"),
+            .set_min_value(1),
         );
 
-        let skip_cmd = ApplicationCommand::new(
+        let mut rewind_cmd = ApplicationCommand::new(
             1,
-            String::from("skip"),
-            String::from("skips the current song"),
-        )
-        .set_name_loc("přeskočit")
-        .set_desc_loc("přeskočí song");
+            String::from("rewind"),
+            String::from("rewinds the current song"),
+        );
 
-        Self {
-            commands: vec![join_cmd, leave_cmd, play_cmd, pause_cmd, skip_cmd],
-        }
-    }
+        rewind_cmd.add_option(
+            ApplicationCommandOption::new(
+                String::from("seconds"),
+                String::from("how many seconds to rewind"),
+                OptionType::Integer,
+                true,
+            )
+            .set_min_value(1),
+        );
 
-    pub fn toulen() -> Self {
-        let mut ts_cmds = ApplicationCommand::new(
+        let mut volume_cmd = ApplicationCommand::new(
             1,
-            String::from("ts"),
-            String::from("ToulenSniffer comannnds"),
-        )
-        .set_desc_loc("ToulenSniffer commandy");
+            String::from("volume"),
+            String::from("changes the playback volume"),
+        );
 
-        ts_cmds.add_option(
+        volume_cmd.add_option(
             ApplicationCommandOption::new(
-                String::from("leaderboard"),
-                String::from("shows top players"),
-                1,
-                false,
+                String::from("level"),
+                String::from("volume in percent"),
+                OptionType::Integer,
+                true,
             )
-            .set_name_loc("žebříčky")
-            .set_desc_loc("zobrazí top hráče"),
+            .set_min_value(0)
+            .set_max_value(200),
+        );
+
+        volume_cmd.add_option(ApplicationCommandOption::new(
+            String::from("default"),
+            String::from("also save as the server default"),
+            OptionType::Boolean,
+            false,
+        ));
+
+        let mut loop_cmd =
+            ApplicationCommand::new(1, String::from("loop"), String::from("sets the loop mode"));
+
+        let mut mode_choice = ApplicationCommandOption::new(
+            String::from("mode"),
+            String::from("what to loop"),
+            OptionType::String,
+            true,
         );
 
-        ts_cmds.add_option(
+        mode_choice.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("Off"),
+            String::from("off"),
+        ));
+
+        mode_choice.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("Track"),
+            String::from("track"),
+        ));
+
+        mode_choice.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("Queue"),
+            String::from("queue"),
+        ));
+
+        loop_cmd.add_option(mode_choice);
+
+        let mut queue_cmd =
+            ApplicationCommand::new(1, String::from("queue"), String::from("shows the queue"));
+
+        queue_cmd.add_option(
             ApplicationCommandOption::new(
-                String::from("download"),
-                String::from("ToulenSniffer download"),
-                1,
+                String::from("page"),
+                String::from("page of the queue"),
+                OptionType::Integer,
                 false,
             )
-            .set_name_loc("stáhnout")
-            .set_desc_loc("odkaz na stažení ToulenSniffer"),
+            .set_min_value(1),
+        );
+
+        let mut remove_cmd = ApplicationCommand::new(
+            1,
+            String::from("remove"),
+            String::from("removes a song from the queue"),
+        );
+
+        remove_cmd.add_option(
+            ApplicationCommandOption::new(
+                String::from("index"),
+                String::from("position in the queue"),
+                OptionType::Integer,
+                true,
+            )
+            .set_min_value(1),
+        );
+
+        let mut move_cmd = ApplicationCommand::new(
+            1,
+            String::from("move"),
+            String::from("moves a song in the queue"),
+        );
+
+        move_cmd.add_option(
+            ApplicationCommandOption::new(
+                String::from("from"),
+                String::from("current position"),
+                OptionType::Integer,
+                true,
+            )
+            .set_min_value(1),
+        );
+
+        move_cmd.add_option(
+            ApplicationCommandOption::new(
+                String::from("to"),
+                String::from("new position"),
+                OptionType::Integer,
+                true,
+            )
+            .set_min_value(1),
+        );
+
+        let clear_cmd =
+            ApplicationCommand::new(1, String::from("clear"), String::from("clears the queue"));
+
+        let shuffle_cmd = ApplicationCommand::new(
+            1,
+            String::from("shuffle"),
+            String::from("shuffles the queue"),
+        );
+
+        let mut skipto_cmd = ApplicationCommand::new(
+            1,
+            String::from("skipto"),
+            String::from("skips to a song in the queue"),
+        );
+
+        skipto_cmd.add_option(
+            ApplicationCommandOption::new(
+                String::from("index"),
+                String::from("position in the queue"),
+                OptionType::Integer,
+                true,
+            )
+            .set_min_value(1),
+        );
+
+        let nowplaying_cmd = ApplicationCommand::new(
+            1,
+            String::from("nowplaying"),
+            String::from("shows the current song"),
+        );
+
+        let mut search_cmd = ApplicationCommand::new(
+            1,
+            String::from("search"),
+            String::from("searches for songs to pick from"),
+        );
+
+        search_cmd.add_option(ApplicationCommandOption::new(
+            String::from("query"),
+            String::from("what to search for"),
+            OptionType::String,
+            true,
+        ));
+
+        let mut search_platform = ApplicationCommandOption::new(
+            String::from("platform"),
+            String::from("platform to search on"),
+            OptionType::String,
+            false,
+        );
+
+        search_platform.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("YouTube"),
+            String::from("ytsearch"),
+        ));
+
+        search_platform.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("YouTube Music"),
+            String::from("ytmsearch"),
+        ));
+
+        search_platform.add_choice(ApplicationCommandOptionChoice::new(
+            String::from("SoundCloud"),
+            String::from("scsearch"),
+        ));
+
+        search_cmd.add_option(search_platform);
+
+        let mut playlist_cmd =
+            ApplicationCommand::new(1, String::from("playlist"), String::from("saved playlists"));
+
+        playlist_cmd.add_option(ApplicationCommandOption::new(
+            String::from("save"),
+            String::from("saves the queue as a playlist"),
+            OptionType::SubCommand,
+            false,
+        ));
+
+        playlist_cmd.add_option(ApplicationCommandOption::new(
+            String::from("import"),
+            String::from("creates a playlist from links"),
+            OptionType::SubCommand,
+            false,
+        ));
+
+        let mut playlist_load = ApplicationCommandOption::new(
+            String::from("load"),
+            String::from("adds a playlist to the queue"),
+            OptionType::SubCommand,
+            false,
+        );
+
+        playlist_load.add_option(
+            ApplicationCommandOption::new(
+                String::from("name"),
+                String::from("name of the playlist"),
+                OptionType::String,
+                true,
+            )
+            .set_autocomplete(true),
         );
 
+        playlist_cmd.add_option(playlist_load);
+
         Self {
-            commands: vec![ts_cmds],
+            commands: vec![
+                Command::new(join_cmd, |i, m| Box::pin(join_channel(i, m))),
+                Command::new(leave_cmd, |i, m| Box::pin(leave_channel(i, m))),
+                Command::new(play_cmd, |i, m| Box::pin(play_track(i, m)))
+                    .set_autocomplete(|i, m| Box::pin(search::suggest_tracks(i, m))),
+                Command::new(pause_cmd, |i, m| Box::pin(pause_track(i, m))),
+                Command::new(skip_cmd, |i, m| Box::pin(skip_track(i, m))),
+                Command::new(seek_cmd, |i, m| Box::pin(seek_to(i, m))),
+                Command::new(forward_cmd, |i, m| Box::pin(seek_forward(i, m))),
+                Command::new(rewind_cmd, |i, m| Box::pin(seek_rewind(i, m))),
+                Command::new(volume_cmd, |i, m| Box::pin(set_volume(i, m))),
+                Command::new(loop_cmd, |i, m| Box::pin(set_loop(i, m))),
+                Command::new(queue_cmd, |i, m| Box::pin(queue::show_queue(i, m))),
+                Command::new(remove_cmd, |i, m| Box::pin(queue::remove_track(i, m))),
+                Command::new(move_cmd, |i, m| Box::pin(queue::move_track(i, m))),
+                Command::new(clear_cmd, |i, m| Box::pin(queue::clear_queue(i, m))),
+                Command::new(shuffle_cmd, |i, m| Box::pin(queue::shuffle_queue(i, m))),
+                Command::new(skipto_cmd, |i, m| Box::pin(queue::skip_to(i, m))),
+                Command::new(nowplaying_cmd, |i, m| Box::pin(now_playing(i, m))),
+                Command::new(search_cmd, |i, m| Box::pin(search::search_tracks(i, m)))
+                    .set_ack(Ack::Ephemeral),
+                Command::new(playlist_cmd, |i, m| {
+                    Box::pin(playlist::playlist_command(i, m))
+                })
+                .set_ack(Ack::Manual)
+                .set_autocomplete(|i, m| Box::pin(playlist::suggest_playlists(i, m))),
+            ],
         }
+        .localized(Locales::get())
+    }
+
+    pub fn toulen() -> Self {
+        let mut ts_cmds = ApplicationCommand::new(
+            1,
+            String::from("ts"),
+            String::from("ToulenSniffer comannnds"),
+        );
+
+        ts_cmds.add_option(ApplicationCommandOption::new(
+            String::from("leaderboard"),
+            String::from("shows top players"),
+            OptionType::SubCommand,
+            false,
+        ));
+
+        ts_cmds.add_option(ApplicationCommandOption::new(
+            String::from("download"),
+            String::from("ToulenSniffer download"),
+            OptionType::SubCommand,
+            false,
+        ));
+
+        Self {
+            commands: vec![Command::new(ts_cmds, |i, _| Box::pin(toulen(i))).set_ack(Ack::Manual)],
+        }
+        .localized(Locales::get())
+    }
+
+    /// Translates every definition with the loaded catalogs.
+    fn localized(mut self, locales: &Locales) -> Self {
+        for command in &mut self.commands {
+            command.definition.localize(locales);
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_time, parse_time};
+
+    #[test]
+    fn parses_seconds() {
+        assert_eq!(parse_time("42"), Some(42));
+        assert_eq!(parse_time(" 90 "), Some(90));
+    }
+
+    #[test]
+    fn parses_minutes_and_seconds() {
+        assert_eq!(parse_time("3:07"), Some(187));
+        assert_eq!(parse_time("00:00"), Some(0));
+    }
+
+    #[test]
+    fn parses_hours_minutes_and_seconds() {
+        assert_eq!(parse_time("1:02:03"), Some(3723));
+    }
+
+    #[test]
+    fn is_the_reverse_of_format_time() {
+        for time in [0, 59, 60, 3599, 3600, 45296] {
+            assert_eq!(parse_time(&format_time(time)), Some(time));
+        }
+    }
+
+    #[test]
+    fn rejects_sixty_seconds_or_minutes() {
+        assert_eq!(parse_time("1:60"), None);
+        assert_eq!(parse_time("1:60:00"), None);
+        assert_eq!(parse_time("1:00:60"), None);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse_time(""), None);
+        assert_eq!(parse_time("1:2:3:4"), None);
+        assert_eq!(parse_time("-5"), None);
+        assert_eq!(parse_time("ab:cd"), None);
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert_eq!(parse_time("5124095576030432:00:00"), None);
+        assert_eq!(parse_time("307445734561825861:00"), None);
+        assert_eq!(parse_time("18446744073709551616"), None);
     }
 }
@@ -0,0 +1,234 @@
+use crate::{
+    client::{
+        components::{ActionRow, Button, ButtonStyle, ComponentEmoji},
+        events::{self, Interaction, InteractionCallbackData},
+        LavalinkClient, LoopMode, Player,
+    },
+    locale::Locales,
+};
+
+use super::{format_time, member_player, CommandError};
+
+/// The panel isn't an answer to anyone, so it's in the locale of the member
+/// who started the player.
+fn panel_message(player: &Player) -> InteractionCallbackData {
+    let locales = Locales::get();
+
+    let locale = player
+        .locale
+        .as_deref()
+        .unwrap_or_else(|| locales.default_locale());
+
+    let text = |key: &str| locales.text(locale, &format!("panel.{key}"), &[]);
+
+    let track = match player.queue.first() {
+        Some(track) => track,
+        None => {
+            return InteractionCallbackData::new().add_embed(
+                events::Embed::new()
+                    .set_title(text("title"))
+                    .set_description(text("ended"))
+                    .set_color(0x0080f0),
+            )
+        }
+    };
+
+    let (link, author) = (track.info.link(), &track.info.author);
+
+    let identifier = &track.info.identifier;
+
+    let lenght = if track.info.is_stream {
+        String::from("LIVE")
+    } else {
+        format_time(track.info.length / 1000)
+    };
+
+    let loop_mode = text(match player.loop_mode {
+        LoopMode::Off => "loop_off",
+        LoopMode::Track => "loop_track",
+        LoopMode::Queue => "loop_queue",
+    });
+
+    let state = if player.paused {
+        format!("{}\n", text("paused"))
+    } else {
+        String::new()
+    };
+
+    let details = locales.text(
+        locale,
+        "panel.details",
+        &[("length", &lenght), ("loop_mode", &loop_mode)],
+    );
+
+    let upcoming = player.queue.len() - 1;
+
+    let mut footer = locales.text(locale, "panel.footer", &[("count", &upcoming)]);
+
+    if let Some(requester) = &track.requester {
+        let requester = locales.text(locale, "panel.requester", &[("requester", &requester.name)]);
+
+        footer.push_str(&format!(" • {requester}"));
+    }
+
+    let embed = events::Embed::new()
+        .set_title(text("title"))
+        .set_description(format!("**{link}**\n{author}\n\n{state}{details}"))
+        .set_color(0x0080f0)
+        .set_thumbnail(events::EmbedThumbnail {
+            url: Some(format!(
+                "https://i.ytimg.com/vi/{identifier}/maxresdefault.jpg"
+            )),
+            proxy_url: None,
+            height: None,
+            width: None,
+        })
+        .set_footer(events::EmbedFooter {
+            text: footer,
+            icon_url: None,
+            proxy_icon_url: None,
+        });
+
+    let pause = if player.paused {
+        Button::new(ButtonStyle::Success, String::from("player:pause"))
+            .set_emoji(ComponentEmoji::unicode("▶️"))
+            .set_label(&text("resume"))
+    } else {
+        Button::new(ButtonStyle::Secondary, String::from("player:pause"))
+            .set_emoji(ComponentEmoji::unicode("⏸️"))
+            .set_label(&text("pause"))
+    };
+
+    let buttons = ActionRow::new()
+        .add_button(pause)
+        .add_button(
+            Button::new(ButtonStyle::Primary, String::from("player:skip"))
+                .set_emoji(ComponentEmoji::unicode("⏭️"))
+                .set_label(&text("skip")),
+        )
+        .add_button(
+            Button::new(ButtonStyle::Danger, String::from("player:stop"))
+                .set_emoji(ComponentEmoji::unicode("⏹️"))
+                .set_label(&text("stop")),
+        )
+        .add_button(
+            Button::new(ButtonStyle::Secondary, String::from("player:loop"))
+                .set_emoji(ComponentEmoji::unicode("🔁"))
+                .set_label(&text("loop")),
+        )
+        .add_button(
+            Button::new(ButtonStyle::Secondary, String::from("player:shuffle"))
+                .set_emoji(ComponentEmoji::unicode("🔀"))
+                .set_label(&text("shuffle"))
+                .set_disabled(upcoming < 2),
+        );
+
+    InteractionCallbackData::new()
+        .add_embed(embed)
+        .add_component(buttons)
+}
+
+/// Edits the control panel of the guild's player in place, a new one is only
+/// posted when there is none yet or the old one was deleted. Any other error
+/// keeps the old panel, reposting on every failed edit would flood the channel.
+pub async fn update_panel(
+    manager: &mut LavalinkClient,
+    guild_id: &str,
+) -> Result<(), CommandError> {
+    let player = match manager.get_player(guild_id) {
+        Some(player) => player,
+        None => return Ok(()),
+    };
+
+    let channel_id = match &player.text_channel_id {
+        Some(channel_id) => channel_id.clone(),
+        None => return Ok(()),
+    };
+
+    let (data, message_id, idle) = (
+        panel_message(player),
+        player.panel_message_id.clone(),
+        player.queue.is_empty(),
+    );
+
+    if let Some(message_id) = message_id {
+        match manager.edit_message(&channel_id, &message_id, &data).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => (),
+            Err(err) => {
+                println!("Error editing player panel: {err}");
+
+                return Ok(());
+            }
+        }
+    }
+
+    if idle {
+        return Ok(());
+    }
+
+    let message_id = manager.send_message(&channel_id, &data).await?;
+
+    if let Some(player) = manager.get_player_mut(guild_id) {
+        player.panel_message_id = Some(message_id);
+    }
+
+    Ok(())
+}
+
+pub async fn panel_button(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+    action: &str,
+) -> Result<(), CommandError> {
+    let player = member_player(interaction, manager).await?;
+
+    let content = match action {
+        "pause" => {
+            let paused = !player.paused;
+
+            player.pause(paused);
+
+            if paused {
+                "panel.paused_reply"
+            } else {
+                "panel.resumed_reply"
+            }
+        }
+        "skip" => match player.skip() {
+            Some(_) => "panel.skipped",
+            None => "errors.nothing_playing",
+        },
+        "stop" => {
+            player.queue.clear();
+            player.playing = false;
+
+            player.stop();
+
+            "panel.stopped"
+        }
+        "loop" => {
+            let (loop_mode, content) = match player.loop_mode {
+                LoopMode::Off => (LoopMode::Track, "loop.track"),
+                LoopMode::Track => (LoopMode::Queue, "loop.queue"),
+                LoopMode::Queue => (LoopMode::Off, "loop.off"),
+            };
+
+            player.loop_mode = loop_mode;
+
+            content
+        }
+        "shuffle" => {
+            player.shuffle();
+
+            "queue.shuffled"
+        }
+        _ => return Err(format!("unknown player action {action}").into()),
+    };
+
+    interaction
+        .edit_original(InteractionCallbackData::new().set_content(&interaction.text(content)))
+        .await?;
+
+    Ok(())
+}
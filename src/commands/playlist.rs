@@ -0,0 +1,318 @@
+use futures_util::{stream, StreamExt};
+use serde_json::Value;
+
+use super::{ack, member_or_new_player, CommandError};
+use crate::{
+    client::{
+        components::{TextInput, TextInputStyle},
+        events::{self, Interaction, InteractionCallbackData, Modal, PERMISSION_MANAGE_GUILD},
+        LavalinkClient, Player,
+    },
+    commands::builder::ApplicationCommandOptionChoice,
+    settings::Playlist,
+};
+
+/// Loading every song is a request to lavalink, so playlists can't be endless.
+const MAX_PLAYLIST_TRACKS: usize = 100;
+/// Songs loaded at once, the event loop waits for the whole playlist.
+const CONCURRENT_LOADS: usize = 10;
+const PROGRESS_EVERY: usize = 10;
+
+/// Saving and importing answer with a modal, which has to be the first response,
+/// so only loading is acknowledged here.
+pub async fn playlist_command(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    match interaction.get_subcommand() {
+        Some("save") => save_dialog(interaction).await,
+        Some("import") => import_dialog(interaction).await,
+        Some("load") => {
+            ack(interaction, 0).await;
+
+            load_playlist(interaction, manager).await
+        }
+        Some(subcommand) => Err(format!("unknown subcommand {subcommand}").into()),
+        None => Err("missing subcommand".into()),
+    }
+}
+
+async fn save_dialog(interaction: &Interaction) -> Result<(), CommandError> {
+    let modal = Modal::new(
+        String::from("playlist:save"),
+        &interaction.text("playlist.save_title"),
+    )
+    .add_text_input(
+        TextInput::new(
+            String::from("name"),
+            TextInputStyle::Short,
+            &interaction.text("playlist.name"),
+        )
+        .set_max_length(100),
+    )
+    .add_text_input(
+        TextInput::new(
+            String::from("description"),
+            TextInputStyle::Paragraph,
+            &interaction.text("playlist.description"),
+        )
+        .set_max_length(300)
+        .set_required(false),
+    );
+
+    Ok(interaction.modal(modal).await?)
+}
+
+async fn import_dialog(interaction: &Interaction) -> Result<(), CommandError> {
+    let modal = Modal::new(
+        String::from("playlist:import"),
+        &interaction.text("playlist.import_title"),
+    )
+    .add_text_input(
+        TextInput::new(
+            String::from("name"),
+            TextInputStyle::Short,
+            &interaction.text("playlist.name"),
+        )
+        .set_max_length(100),
+    )
+    .add_text_input(
+        TextInput::new(
+            String::from("urls"),
+            TextInputStyle::Paragraph,
+            &interaction.text("playlist.urls"),
+        )
+        .set_placeholder(&interaction.text("playlist.urls_placeholder")),
+    );
+
+    Ok(interaction.modal(modal).await?)
+}
+
+pub async fn save_submit(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let name = playlist_name(interaction)?;
+    let description = interaction.get_input("description").unwrap_or_default();
+
+    let tracks = manager
+        .get_player(&interaction.guild_id)
+        .map(|player| {
+            player
+                .queue
+                .iter()
+                .filter_map(|track| track.info.uri.clone())
+                .take(MAX_PLAYLIST_TRACKS)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    if tracks.is_empty() {
+        return Err(CommandError::user(interaction.text("errors.queue_empty")));
+    }
+
+    let count = tracks.len();
+
+    save_playlist(interaction, manager, name, description, tracks).await?;
+
+    interaction
+        .edit_original(InteractionCallbackData::new().set_content(
+            &interaction.text_with("playlist.saved", &[("name", &name), ("count", &count)]),
+        ))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn import_submit(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let name = playlist_name(interaction)?;
+    let urls = interaction.get_input("urls").ok_or("missing urls")?;
+
+    let tracks = urls
+        .split_whitespace()
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+        .map(String::from)
+        .take(MAX_PLAYLIST_TRACKS)
+        .collect::<Vec<_>>();
+
+    if tracks.is_empty() {
+        return Err(CommandError::user(interaction.text("playlist.no_urls")));
+    }
+
+    let count = tracks.len();
+
+    save_playlist(interaction, manager, name, "", tracks).await?;
+
+    interaction
+        .edit_original(InteractionCallbackData::new().set_content(
+            &interaction.text_with("playlist.imported", &[("name", &name), ("count", &count)]),
+        ))
+        .await?;
+
+    Ok(())
+}
+
+/// Discord only checks that the name isn't empty before trimming, and an empty
+/// name would break the autocomplete of every playlist in the guild.
+fn playlist_name(interaction: &Interaction) -> Result<&str, CommandError> {
+    let name = interaction.get_input("name").ok_or("missing name")?.trim();
+
+    if name.is_empty() {
+        return Err(CommandError::user(interaction.text("playlist.empty_name")));
+    }
+
+    Ok(name)
+}
+
+async fn save_playlist(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+    name: &str,
+    description: &str,
+    tracks: Vec<String>,
+) -> Result<(), CommandError> {
+    // members can't overwrite each other's playlists, server managers can
+    let overwrite_any = interaction.member.has_permission(PERMISSION_MANAGE_GUILD);
+
+    let saved = manager.settings.save_playlist(
+        &interaction.guild_id,
+        Playlist {
+            name: String::from(name),
+            description: String::from(description),
+            author_id: interaction.member.user.id.clone(),
+            tracks,
+        },
+        overwrite_any,
+    );
+
+    if let Err(author) = saved {
+        return Err(CommandError::user(interaction.text_with(
+            "playlist.not_owner",
+            &[("name", &name), ("author", &author)],
+        )));
+    }
+
+    Ok(manager.settings.save().await?)
+}
+
+async fn load_playlist(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let name = interaction.get_string("name")?;
+
+    let playlist = match manager.settings.playlist(&interaction.guild_id, name) {
+        Some(playlist) => playlist.clone(),
+        None => {
+            return Err(CommandError::user(
+                interaction.text_with("playlist.not_found", &[("name", &name)]),
+            ))
+        }
+    };
+
+    let player = member_or_new_player(interaction, manager).await?;
+
+    let total = playlist.tracks.len();
+
+    let searching: &Player = player;
+
+    // buffered keeps the order of the playlist while several songs load at once
+    let mut searches = stream::iter(&playlist.tracks)
+        .map(|url| async move { (url, searching.search(url, None).await) })
+        .buffered(CONCURRENT_LOADS);
+
+    let (mut loaded, mut index) = (Vec::with_capacity(total), 0);
+
+    let mut progress_id: Option<String> = None;
+
+    while let Some((url, result)) = searches.next().await {
+        index += 1;
+
+        match result {
+            Ok(result) => loaded.push(result.tracks),
+            Err(err) => println!("Error loading {url} from playlist {name}: {err:?}"),
+        }
+
+        // big playlists take a while, so a followup shows how far the loading got
+        if index % PROGRESS_EVERY == 0 && index < total {
+            let progress = InteractionCallbackData::new().set_content(&interaction.text_with(
+                "playlist.loading",
+                &[("name", &name), ("index", &index), ("total", &total)],
+            ));
+
+            let updated = match &progress_id {
+                Some(message_id) => interaction.edit_followup(message_id, progress).await,
+                None => interaction
+                    .create_followup(progress)
+                    .await
+                    .map(|message_id| progress_id = Some(message_id)),
+            };
+
+            if let Err(err) = updated {
+                println!("Error updating playlist progress: {err:?}");
+            }
+        }
+    }
+
+    drop(searches);
+
+    // the summary goes to the original response, the progress isn't needed anymore
+    if let Some(message_id) = progress_id {
+        if let Err(err) = interaction.delete_followup(&message_id).await {
+            println!("Error deleting playlist progress: {err:?}");
+        }
+    }
+
+    let mut added = 0;
+
+    for track in loaded.into_iter().flatten() {
+        player.play(track.set_requester(&interaction.member));
+
+        added += 1;
+    }
+
+    let (playlist_name, description) = (&playlist.name, &playlist.description);
+
+    let embed = events::Embed::new()
+        .set_description(interaction.text_with(
+            "playlist.loaded",
+            &[
+                ("name", playlist_name),
+                ("description", description),
+                ("count", &added),
+            ],
+        ))
+        .set_color(0x0080f0);
+
+    interaction
+        .edit_original(InteractionCallbackData::new().add_embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn suggest_playlists(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let typed = match interaction.get_focused() {
+        Some((_, Value::String(typed))) => typed.to_lowercase(),
+        _ => String::new(),
+    };
+
+    let choices = manager
+        .settings
+        .guild(&interaction.guild_id)
+        .playlists
+        .into_iter()
+        .filter(|playlist| !playlist.name.is_empty())
+        .filter(|playlist| playlist.name.to_lowercase().contains(&typed))
+        .take(25)
+        .map(|playlist| ApplicationCommandOptionChoice::new(playlist.name.clone(), playlist.name))
+        .collect();
+
+    Ok(interaction.autocomplete(choices).await?)
+}
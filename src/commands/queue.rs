@@ -0,0 +1,260 @@
+use crate::client::{
+    events::{self, Interaction, InteractionCallbackData, PERMISSION_MANAGE_CHANNELS},
+    LavalinkClient,
+};
+
+use super::{format_time, member_player, CommandError};
+
+const PAGE_SIZE: usize = 10;
+
+pub async fn show_queue(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let player = member_player(interaction, manager).await?;
+
+    let current = match player.queue.first() {
+        Some(track) => track,
+        None => return Err(CommandError::user(interaction.text("errors.queue_empty"))),
+    };
+
+    let upcoming = &player.queue[1..];
+
+    let pages = upcoming.len().div_ceil(PAGE_SIZE).max(1);
+
+    let page = interaction
+        .get_integer("page")
+        .unwrap_or(1)
+        .clamp(1, pages as i64) as usize;
+
+    let track = current.info.link();
+
+    let lenght = if current.info.is_stream {
+        String::from("LIVE")
+    } else {
+        format_time(current.info.length / 1000)
+    };
+
+    let mut description = interaction.text_with(
+        "queue.now_playing",
+        &[("track", &track), ("length", &lenght)],
+    );
+
+    description.push_str("\n\n");
+
+    for (index, track) in upcoming
+        .iter()
+        .enumerate()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+    {
+        let link = track.info.link();
+        let position = index + 1;
+
+        let lenght = if track.info.is_stream {
+            String::from("LIVE")
+        } else {
+            format_time(track.info.length / 1000)
+        };
+
+        let requester = track
+            .requester
+            .as_ref()
+            .map(|r| format!(" • {}", r.name))
+            .unwrap_or_default();
+
+        description.push_str(&format!("`{position}.` {link} `{lenght}`{requester}\n"));
+    }
+
+    // a stream's position isn't part of the summed lengths, so it's not subtracted
+    let played = if current.info.is_stream {
+        0
+    } else {
+        player.position()
+    };
+
+    let remaining = player
+        .queue
+        .iter()
+        .filter(|t| !t.info.is_stream)
+        .map(|t| t.info.length)
+        .sum::<u64>()
+        .saturating_sub(played);
+
+    let (count, remaining) = (upcoming.len(), format_time(remaining / 1000));
+
+    let embed = events::Embed::new()
+        .set_title(interaction.text("queue.title"))
+        .set_description(description)
+        .set_color(0x0080f0)
+        .set_footer(events::EmbedFooter {
+            text: interaction.text_with(
+                "queue.footer",
+                &[
+                    ("page", &page),
+                    ("pages", &pages),
+                    ("count", &count),
+                    ("remaining", &remaining),
+                ],
+            ),
+            icon_url: None,
+            proxy_icon_url: None,
+        });
+
+    interaction
+        .edit_original(InteractionCallbackData::new().add_embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn remove_track(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let index = interaction.get_integer("index")?;
+
+    let player = member_player(interaction, manager).await?;
+
+    let member = &interaction.member;
+
+    let own_track = player
+        .queue
+        .get(index as usize)
+        .and_then(|t| t.requester.as_ref())
+        .is_some_and(|r| r.user_id == member.user.id);
+
+    if index > 0
+        && (index as usize) < player.queue.len()
+        && !own_track
+        && !member.has_permission(PERMISSION_MANAGE_CHANNELS)
+    {
+        return Err(CommandError::user(interaction.text("queue.remove_not_own")));
+    }
+
+    let track = match player.remove(index as usize) {
+        Some(track) => track,
+        None => {
+            return Err(CommandError::user(
+                interaction.text_with("queue.no_track", &[("index", &index)]),
+            ))
+        }
+    };
+
+    let link = track.info.link();
+
+    interaction
+        .edit_original(
+            InteractionCallbackData::new().add_embed(
+                events::Embed::new()
+                    .set_description(interaction.text_with("queue.removed", &[("track", &link)]))
+                    .set_color(0x0080f0),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn move_track(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let from = interaction.get_integer("from")?;
+
+    let to = interaction.get_integer("to")?;
+
+    let player = member_player(interaction, manager).await?;
+
+    let track = match player.move_track(from as usize, to as usize) {
+        Some(track) => track,
+        None => {
+            return Err(CommandError::user(
+                interaction.text("queue.invalid_position"),
+            ))
+        }
+    };
+
+    let link = track.info.link();
+
+    interaction
+        .edit_original(
+            InteractionCallbackData::new().add_embed(
+                events::Embed::new()
+                    .set_description(
+                        interaction.text_with("queue.moved", &[("track", &link), ("to", &to)]),
+                    )
+                    .set_color(0x0080f0),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn clear_queue(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let player = member_player(interaction, manager).await?;
+
+    let removed = player.clear();
+
+    interaction
+        .edit_original(
+            InteractionCallbackData::new()
+                .set_content(&interaction.text_with("queue.cleared", &[("count", &removed)])),
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn shuffle_queue(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let player = member_player(interaction, manager).await?;
+
+    player.shuffle();
+
+    interaction
+        .edit_original(
+            InteractionCallbackData::new().set_content(&interaction.text("queue.shuffled")),
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn skip_to(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let index = interaction.get_integer("index")?;
+
+    let player = member_player(interaction, manager).await?;
+
+    let track = match player.skip_to(index as usize) {
+        Some(track) => track,
+        None => {
+            return Err(CommandError::user(
+                interaction.text_with("queue.no_track", &[("index", &index)]),
+            ))
+        }
+    };
+
+    let link = track.info.link();
+
+    interaction
+        .edit_original(
+            InteractionCallbackData::new().add_embed(
+                events::Embed::new()
+                    .set_description(interaction.text_with("queue.skipped_to", &[("track", &link)]))
+                    .set_color(0x0080f0),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
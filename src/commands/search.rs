@@ -0,0 +1,176 @@
+use serde_json::Value;
+
+use crate::{
+    client::{
+        components::{ActionRow, SelectMenu, SelectOption},
+        events::{self, Interaction, InteractionCallbackData},
+        LavalinkClient,
+    },
+    commands::builder::ApplicationCommandOptionChoice,
+};
+
+use super::{format_time, member_or_new_player, queued_embed, CommandError};
+
+/// Discord allows up to 25 options in a select menu, more would not fit on screen anyway.
+const SEARCH_RESULTS: usize = 10;
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return String::from(text);
+    }
+
+    let mut text = text.chars().take(max - 1).collect::<String>();
+    text.push('…');
+
+    text
+}
+
+pub async fn search_tracks(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let query = interaction.get_string("query")?;
+
+    let platform = interaction.get_string("platform").unwrap_or("ytsearch");
+
+    let result = manager.search(query, Some(platform)).await?;
+
+    if result.tracks.is_empty() {
+        return Err(CommandError::user(interaction.text("errors.nothing_found")));
+    }
+
+    let tracks = result
+        .tracks
+        .into_iter()
+        .take(SEARCH_RESULTS)
+        .collect::<Vec<_>>();
+
+    let custom_id = format!("search:{}", interaction.id);
+
+    let mut menu = SelectMenu::string(custom_id.clone())
+        .set_placeholder(&interaction.text("search.placeholder"));
+
+    for (index, track) in tracks.iter().enumerate() {
+        let lenght = if track.info.is_stream {
+            String::from("LIVE")
+        } else {
+            format_time(track.info.length / 1000)
+        };
+
+        menu = menu.add_option(
+            SelectOption::new(truncate(&track.info.title, 100), index.to_string())
+                .set_description(truncate(&format!("{} • {lenght}", track.info.author), 100)),
+        );
+    }
+
+    manager.add_search(custom_id, interaction.member.user.id.clone(), tracks);
+
+    let embed = events::Embed::new()
+        .set_description(interaction.text_with("search.results", &[("query", &query)]))
+        .set_color(0x0080f0);
+
+    interaction
+        .edit_original(
+            InteractionCallbackData::new()
+                .add_embed(embed)
+                .add_component(ActionRow::new().add_select_menu(menu)),
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn pick_track(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let guild_id = &interaction.guild_id;
+    let channel_id = match interaction.member.get_voice_channel() {
+        Some(channel_id) => channel_id,
+        None => return Err(CommandError::user(interaction.text("errors.not_in_voice"))),
+    };
+
+    if manager
+        .get_player(guild_id)
+        .is_some_and(|player| player.channel_id != *channel_id)
+    {
+        return Err(CommandError::user(
+            interaction.text("errors.not_same_voice"),
+        ));
+    }
+
+    let custom_id = interaction.get_custom_id().ok_or("missing custom_id")?;
+
+    let index = interaction
+        .get_values()
+        .first()
+        .and_then(|value| value.parse::<usize>().ok())
+        .ok_or("missing selected value")?;
+
+    let track = match manager
+        .take_search(custom_id, &interaction.member.user.id)
+        .and_then(|search| search.tracks.into_iter().nth(index))
+    {
+        Some(track) => track,
+        None => return Err(CommandError::user(interaction.text("search.expired"))),
+    };
+
+    let player = member_or_new_player(interaction, manager).await?;
+
+    interaction
+        .edit_original(InteractionCallbackData::new().add_embed(queued_embed(interaction, &track)))
+        .await?;
+
+    player.play(track.set_requester(&interaction.member));
+
+    Ok(())
+}
+
+/// Autocomplete for the `query` option of `/play`, the picked value is the song url.
+pub async fn suggest_tracks(
+    interaction: &Interaction,
+    manager: &mut LavalinkClient,
+) -> Result<(), CommandError> {
+    let query = match interaction.get_focused() {
+        Some(("query", Value::String(query))) => query,
+        _ => return Ok(interaction.autocomplete(vec![]).await?),
+    };
+
+    if query.trim().is_empty() || query.starts_with("https://") || query.starts_with("http://") {
+        return Ok(interaction.autocomplete(vec![]).await?);
+    }
+
+    let platform = interaction.get_string("platform").unwrap_or("ytsearch");
+
+    let tracks = match manager
+        .suggest(&interaction.member.user.id, query, platform)
+        .await
+    {
+        Ok(tracks) => tracks,
+        Err(err) => {
+            println!("Error searching for suggestions: {err:?}");
+
+            vec![]
+        }
+    };
+
+    let choices = tracks
+        .into_iter()
+        .map(|track| {
+            let name = truncate(
+                &format!("{} • {}", track.info.title, track.info.author),
+                100,
+            );
+
+            // choice values are limited to 100 characters as well
+            let value = match track.info.uri {
+                Some(uri) if uri.chars().count() <= 100 => uri,
+                _ => truncate(&track.info.title, 100),
+            };
+
+            ApplicationCommandOptionChoice::new(name, value)
+        })
+        .collect();
+
+    Ok(interaction.autocomplete(choices).await?)
+}
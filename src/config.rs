@@ -1,9 +1,13 @@
 use serde::Deserialize;
 
+use crate::client::LavalinkVersion;
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub discord: DiscordConfig,
-    pub lavalink: LavalinkConfig,
+    pub lavalink: Vec<LavalinkConfig>,
+    #[serde(default)]
+    pub locale: LocaleConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -15,9 +19,40 @@ pub struct DiscordConfig {
 
 #[derive(Debug, Deserialize)]
 pub struct LavalinkConfig {
+    pub name: Option<String>,
     pub host: String,
     pub port: u16,
     pub password: String,
+    #[serde(default)]
+    pub version: LavalinkVersion,
+}
+
+impl LavalinkConfig {
+    /// Nodes without a name are called by their address.
+    pub fn name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{}:{}", self.host, self.port),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LocaleConfig {
+    /// Used when there is no catalog for the locale of the interaction.
+    pub default: String,
+    /// Directory with the `<locale>.toml` message catalogs.
+    pub path: String,
+}
+
+impl Default for LocaleConfig {
+    fn default() -> Self {
+        Self {
+            default: String::from("cs"),
+            path: String::from("locales"),
+        }
+    }
 }
 
 impl Config {
@@ -26,6 +61,22 @@ impl Config {
             .await
             .map_err(|_| "Failed to read config.toml")?;
 
-        toml::from_slice::<Config>(&config).map_err(|_| String::from("Failed to parse config.toml"))
+        let config = toml::from_slice::<Config>(&config)
+            .map_err(|_| String::from("Failed to parse config.toml"))?;
+
+        // players and events find their node by name
+        let mut names = Vec::new();
+
+        for node in &config.lavalink {
+            let name = node.name();
+
+            if names.contains(&name) {
+                return Err(format!("Lavalink node {name} is in config.toml twice"));
+            }
+
+            names.push(name);
+        }
+
+        Ok(config)
     }
 }
@@ -1,4 +1,6 @@
 pub mod client;
 pub mod commands;
 pub mod config;
+pub mod locale;
+pub mod settings;
 pub mod toulen;
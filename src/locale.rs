@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+static LOCALES: OnceLock<Locales> = OnceLock::new();
+
+/// Message catalogs of every locale, one `<locale>.toml` file per locale where
+/// tables group the keys, e.g. `errors.not_in_voice`.
+#[derive(Debug, Default)]
+pub struct Locales {
+    default_locale: String,
+    catalogs: HashMap<String, HashMap<String, String>>,
+}
+
+impl Locales {
+    pub async fn load(path: &str, default_locale: String) -> Result<Self, String> {
+        let mut dir = tokio::fs::read_dir(path)
+            .await
+            .map_err(|err| format!("Failed to read {path}: {err}"))?;
+
+        let mut catalogs = HashMap::new();
+
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(|err| format!("Failed to read {path}: {err}"))?
+        {
+            let file = entry.path();
+
+            if file.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+                continue;
+            }
+
+            let locale = match file.file_stem().and_then(|stem| stem.to_str()) {
+                Some(locale) => String::from(locale),
+                None => continue,
+            };
+
+            let content = tokio::fs::read(&file)
+                .await
+                .map_err(|err| format!("Failed to read {}: {err}", file.display()))?;
+
+            let table = toml::from_slice::<toml::Value>(&content)
+                .map_err(|err| format!("Failed to parse {}: {err}", file.display()))?;
+
+            let mut catalog = HashMap::new();
+
+            flatten("", &table, &mut catalog);
+
+            catalogs.insert(locale, catalog);
+        }
+
+        if !catalogs.contains_key(&default_locale) {
+            return Err(format!(
+                "No catalog for the default locale {default_locale} in {path}"
+            ));
+        }
+
+        Ok(Self {
+            default_locale,
+            catalogs,
+        })
+    }
+
+    /// Makes the catalogs available through `Locales::get`, only the first call counts.
+    pub fn init(self) {
+        if LOCALES.set(self).is_err() {
+            println!("Locales are already loaded");
+        }
+    }
+
+    /// The loaded catalogs, without `init` every message is just its key.
+    pub fn get() -> &'static Locales {
+        LOCALES.get_or_init(Locales::default)
+    }
+
+    /// Every locale that has a catalog.
+    pub fn locales(&self) -> impl Iterator<Item = &str> {
+        self.catalogs.keys().map(String::as_str)
+    }
+
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    /// Message `key` in `locale` with every `{name}` replaced by its argument.
+    /// Falls back to the language without the region (`en-US` to `en`), then to
+    /// the default locale and at last to the key itself.
+    pub fn text(&self, locale: &str, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let language = locale.split('-').next().unwrap_or(locale);
+
+        let message = [locale, language, self.default_locale.as_str()]
+            .iter()
+            .find_map(|locale| self.catalogs.get(*locale)?.get(key));
+
+        match message {
+            Some(message) => substitute(message, args),
+            None => String::from(key),
+        }
+    }
+
+    /// Message `key` of every catalog that has it, without any fallback.
+    pub fn translations<'l>(&'l self, key: &'l str) -> impl Iterator<Item = (&'l str, &'l str)> {
+        self.catalogs.iter().filter_map(move |(locale, catalog)| {
+            Some((locale.as_str(), catalog.get(key)?.as_str()))
+        })
+    }
+}
+
+/// Replaces the `{name}` placeholders of `template` in one pass, so an argument
+/// that itself contains `{other}` is left as it is. Unknown placeholders stay.
+fn substitute(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+
+        rest = &rest[start..];
+
+        let arg = rest.find('}').and_then(|end| {
+            let (_, value) = args.iter().find(|(name, _)| *name == &rest[1..end])?;
+
+            Some((end, value))
+        });
+
+        match arg {
+            Some((end, value)) => {
+                message.push_str(&value.to_string());
+
+                rest = &rest[end + 1..];
+            }
+            None => {
+                message.push('{');
+
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    message.push_str(rest);
+
+    message
+}
+
+fn flatten(prefix: &str, value: &toml::Value, catalog: &mut HashMap<String, String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+
+                flatten(&key, value, catalog);
+            }
+        }
+        toml::Value::String(message) => {
+            catalog.insert(String::from(prefix), message.clone());
+        }
+        value => {
+            catalog.insert(String::from(prefix), value.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::Locales;
+
+    fn locales() -> Locales {
+        let catalog = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(key, message)| (String::from(*key), String::from(*message)))
+                .collect::<HashMap<_, _>>()
+        };
+
+        Locales {
+            default_locale: String::from("en"),
+            catalogs: HashMap::from([
+                (
+                    String::from("en"),
+                    catalog(&[
+                        ("greeting", "Hello"),
+                        ("farewell", "Bye"),
+                        ("only_default", "Default"),
+                        ("queued", "Queued {title} by {author}"),
+                    ]),
+                ),
+                (
+                    String::from("es"),
+                    catalog(&[("greeting", "Hola"), ("farewell", "Adiós")]),
+                ),
+                (String::from("es-ES"), catalog(&[("greeting", "Buenas")])),
+            ]),
+        }
+    }
+
+    #[test]
+    fn text_falls_back_from_locale_to_language_to_default_to_key() {
+        let locales = locales();
+
+        assert_eq!(locales.text("es-ES", "greeting", &[]), "Buenas");
+        assert_eq!(locales.text("es-ES", "farewell", &[]), "Adiós");
+        assert_eq!(locales.text("es-419", "greeting", &[]), "Hola");
+        assert_eq!(locales.text("es-ES", "only_default", &[]), "Default");
+        assert_eq!(locales.text("fr", "greeting", &[]), "Hello");
+        assert_eq!(locales.text("es-ES", "missing", &[]), "missing");
+    }
+
+    #[test]
+    fn text_replaces_only_placeholders_of_the_template() {
+        let locales = locales();
+
+        assert_eq!(
+            locales.text("en", "queued", &[("title", &"{author}"), ("author", &"me")]),
+            "Queued {author} by me"
+        );
+        assert_eq!(
+            locales.text("en", "queued", &[("title", &"song")]),
+            "Queued song by {author}"
+        );
+        assert_eq!(
+            locales.text("en", "queued", &[("title", &1), ("author", &"{")]),
+            "Queued 1 by {"
+        );
+    }
+}
@@ -1,14 +1,49 @@
 use std::env;
+use std::process::ExitCode;
 
 use franta_rust::{
     client::{Client, ClientBuilderOptions, LavalinkBuilderOptions},
-    commands::Commands,
-    config::Config,
+    config::{Config, LocaleConfig},
+    locale::Locales,
 };
 
+mod cli;
+
 #[tokio::main]
-async fn main() {
-    let config = Config::new().await.unwrap();
+async fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    // exporting only needs the definitions, so it works without a config
+    if cli::is_offline(&args) {
+        if let Err(err) = load_locales(&LocaleConfig::default()).await {
+            println!("Error: {err}");
+
+            return ExitCode::FAILURE;
+        }
+
+        return cli::run_offline(&args);
+    }
+
+    let config = match Config::new().await {
+        Ok(config) => config,
+        Err(err) => {
+            println!("Error: {err}");
+
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // the command definitions are translated from the catalogs
+    if let Err(err) = load_locales(&config.locale).await {
+        println!("Error: {err}");
+
+        return ExitCode::FAILURE;
+    }
+
+    println!(
+        "Loaded locales: {:?}",
+        Locales::get().locales().collect::<Vec<_>>()
+    );
 
     let client = Client::new(
         ClientBuilderOptions {
@@ -16,42 +51,36 @@ async fn main() {
             token: config.discord.token,
             app_id: config.discord.app_id,
         },
-        LavalinkBuilderOptions {
-            host: config.lavalink.host,
-            port: config.lavalink.port,
-            password: config.lavalink.password,
-        },
+        config
+            .lavalink
+            .into_iter()
+            .map(|node| LavalinkBuilderOptions {
+                name: node.name(),
+                host: node.host,
+                port: node.port,
+                password: node.password,
+                version: node.version,
+            })
+            .collect(),
     );
 
-    let commands = Commands::new();
-    let ts_commdands = Commands::toulen();
-
-    match env::args().nth(1).as_deref() {
-        Some("toulen") => {
-            for command in ts_commdands.commands {
-                if let Err(err) = client
-                    .add_guild_command(String::from("456060911573008385"), command)
-                    .await
-                {
-                    println!("Error while adding guild command: {err}");
-                }
-            }
-
-            panic!("Done adding commands");
-        }
-        Some("global") => {
-            for command in commands.commands {
-                if let Err(err) = client.add_command(command).await {
-                    println!("Error while adding guild command: {err}");
-                }
+    if !args.is_empty() {
+        return cli::run(&client, &args).await;
+    }
 
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-            }
+    if let Err(err) = client.login().await {
+        println!("Error: {err}");
 
-            panic!("Done adding commands");
-        }
-        _ => {}
+        return ExitCode::FAILURE;
     }
 
-    client.login().await.unwrap();
+    ExitCode::SUCCESS
+}
+
+async fn load_locales(config: &LocaleConfig) -> Result<(), String> {
+    Locales::load(&config.path, config.default.clone())
+        .await?
+        .init();
+
+    Ok(())
 }
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_PATH: &str = "settings.json";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    guilds: HashMap<String, GuildSettings>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GuildSettings {
+    #[serde(default = "default_volume")]
+    pub volume: u8,
+    #[serde(default)]
+    pub playlists: Vec<Playlist>,
+}
+
+/// Tracks are stored as urls, so they get loaded again on whatever node plays them.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Playlist {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub author_id: String,
+    pub tracks: Vec<String>,
+}
+
+fn default_volume() -> u8 {
+    100
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            volume: default_volume(),
+            playlists: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Missing or broken settings file just means every guild starts with defaults.
+    pub async fn load() -> Self {
+        let settings = match tokio::fs::read(SETTINGS_PATH).await {
+            Ok(settings) => settings,
+            Err(_) => return Self::default(),
+        };
+
+        match serde_json::from_slice(&settings) {
+            Ok(settings) => settings,
+            Err(err) => {
+                println!("Failed to parse {SETTINGS_PATH}: {err}");
+
+                Self::default()
+            }
+        }
+    }
+
+    pub async fn save(&self) -> Result<(), String> {
+        let settings = serde_json::to_vec_pretty(self)
+            .map_err(|_| String::from("Failed to serialize settings"))?;
+
+        tokio::fs::write(SETTINGS_PATH, settings)
+            .await
+            .map_err(|_| format!("Failed to write {SETTINGS_PATH}"))
+    }
+
+    pub fn guild(&self, guild_id: &str) -> GuildSettings {
+        self.guilds.get(guild_id).cloned().unwrap_or_default()
+    }
+
+    pub fn playlist(&self, guild_id: &str, name: &str) -> Option<&Playlist> {
+        self.guilds
+            .get(guild_id)?
+            .playlists
+            .iter()
+            .find(|playlist| playlist.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Replaces a playlist with the same name, but only one of the same author
+    /// unless `overwrite_any` is set. Otherwise returns the id of its author.
+    pub fn save_playlist(
+        &mut self,
+        guild_id: &str,
+        playlist: Playlist,
+        overwrite_any: bool,
+    ) -> Result<(), String> {
+        if let Some(existing) = self.playlist(guild_id, &playlist.name) {
+            if existing.author_id != playlist.author_id && !overwrite_any {
+                return Err(existing.author_id.clone());
+            }
+        }
+
+        let playlists = &mut self.guild_mut(guild_id).playlists;
+
+        playlists.retain(|p| !p.name.eq_ignore_ascii_case(&playlist.name));
+        playlists.push(playlist);
+
+        Ok(())
+    }
+
+    pub fn guild_mut(&mut self, guild_id: &str) -> &mut GuildSettings {
+        self.guilds.entry(String::from(guild_id)).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Playlist, Settings};
+
+    fn playlist(name: &str, author_id: &str) -> Playlist {
+        Playlist {
+            name: String::from(name),
+            description: String::new(),
+            author_id: String::from(author_id),
+            tracks: vec![format!("https://example.com/{author_id}")],
+        }
+    }
+
+    #[test]
+    fn author_overwrites_own_playlist() {
+        let mut settings = Settings::default();
+
+        assert!(settings
+            .save_playlist("guild", playlist("mix", "a"), false)
+            .is_ok());
+        assert!(settings
+            .save_playlist("guild", playlist("MIX", "a"), false)
+            .is_ok());
+
+        assert_eq!(settings.guild("guild").playlists.len(), 1);
+        assert_eq!(settings.playlist("guild", "mix").unwrap().name, "MIX");
+    }
+
+    #[test]
+    fn others_can_not_overwrite_a_playlist() {
+        let mut settings = Settings::default();
+
+        settings
+            .save_playlist("guild", playlist("mix", "a"), false)
+            .unwrap();
+
+        assert_eq!(
+            settings.save_playlist("guild", playlist("Mix", "b"), false),
+            Err(String::from("a"))
+        );
+        assert_eq!(settings.playlist("guild", "mix").unwrap().author_id, "a");
+    }
+
+    #[test]
+    fn managers_overwrite_any_playlist() {
+        let mut settings = Settings::default();
+
+        settings
+            .save_playlist("guild", playlist("mix", "a"), false)
+            .unwrap();
+
+        assert!(settings
+            .save_playlist("guild", playlist("mix", "b"), true)
+            .is_ok());
+        assert_eq!(settings.playlist("guild", "mix").unwrap().author_id, "b");
+    }
+
+    #[test]
+    fn same_name_in_another_guild_is_a_different_playlist() {
+        let mut settings = Settings::default();
+
+        settings
+            .save_playlist("guild", playlist("mix", "a"), false)
+            .unwrap();
+
+        assert!(settings
+            .save_playlist("other", playlist("mix", "b"), false)
+            .is_ok());
+    }
+}